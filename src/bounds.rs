@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+
+/// An axis-aligned rectangle in canvas coordinates
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Bounds {
+    pub fn new(x: i32, y: i32, w: u32, h: u32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.w as i32
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.h as i32
+    }
+
+    /// Checks if the bounds share any area. Zero-sized bounds never overlap.
+    pub fn overlaps(&self, other: &Bounds) -> bool {
+        self.w > 0
+            && self.h > 0
+            && other.w > 0
+            && other.h > 0
+            && self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// Returns the overlapping area. Disjoint bounds produce a zero-sized result.
+    pub fn intersection(&self, other: &Bounds) -> Bounds {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let w = (self.right().min(other.right()) - x).max(0) as u32;
+        let h = (self.bottom().min(other.bottom()) - y).max(0) as u32;
+        Bounds { x, y, w, h }
+    }
+
+    /// Splits the bounds into `cols` by `rows` cells in row-major order. When the size
+    /// doesn't divide evenly, cells differ by at most a pixel and still cover the bounds.
+    pub fn grid(&self, cols: u32, rows: u32) -> impl Iterator<Item = Bounds> {
+        let Bounds { x, y, w, h } = *self;
+        // Cell edges are rounded down, so there are no gaps between cells
+        let edge = |len: u32, n: u32, i: u32| (len as u64 * i as u64 / n as u64) as i32;
+        (0..rows).flat_map(move |row| {
+            (0..cols).map(move |col| {
+                let (x0, x1) = (edge(w, cols, col), edge(w, cols, col + 1));
+                let (y0, y1) = (edge(h, rows, row), edge(h, rows, row + 1));
+                Bounds::new(x + x0, y + y0, (x1 - x0) as u32, (y1 - y0) as u32)
+            })
+        })
+    }
+
+    /// Splits the bounds into `tile_w` by `tile_h` cells in row-major order. Cells in
+    /// the last column and row are cropped to fit.
+    pub fn tiles(&self, tile_w: u32, tile_h: u32) -> impl Iterator<Item = Bounds> {
+        let Bounds { x, y, w, h } = *self;
+        let (tile_w, tile_h) = (tile_w.max(1), tile_h.max(1));
+        (0..h).step_by(tile_h as usize).flat_map(move |oy| {
+            (0..w).step_by(tile_w as usize).map(move |ox| {
+                let tw = tile_w.min(w - ox);
+                let th = tile_h.min(h - oy);
+                Bounds::new(x + ox as i32, y + oy as i32, tw, th)
+            })
+        })
+    }
+
+    /// Splits the bounds into `n` cells side by side with `gap` pixels between
+    /// them. Pixels that don't divide evenly go to the last cell.
+    pub fn split_horizontal(&self, n: u32, gap: u32) -> Vec<Bounds> {
+        split_span(self.w, n, gap)
+            .map(|(offset, w)| Bounds::new(self.x + offset, self.y, w, self.h))
+            .collect()
+    }
+
+    /// Splits the bounds into `n` cells stacked top to bottom with `gap` pixels
+    /// between them. Pixels that don't divide evenly go to the last cell.
+    pub fn split_vertical(&self, n: u32, gap: u32) -> Vec<Bounds> {
+        split_span(self.h, n, gap)
+            .map(|(offset, h)| Bounds::new(self.x, self.y + offset, self.w, h))
+            .collect()
+    }
+
+    /// Positions a `(w, h)` child within the bounds. A child larger than the
+    /// bounds overhangs on the side opposite the alignment, or evenly when centered.
+    pub fn align_within(&self, child: (u32, u32), h: HAlign, v: VAlign) -> Bounds {
+        let (w, ch) = child;
+        let x = match h {
+            HAlign::Left => self.x as i64,
+            HAlign::Center => self.x as i64 + (self.w as i64 - w as i64) / 2,
+            HAlign::Right => self.right() as i64 - w as i64,
+        };
+        let y = match v {
+            VAlign::Top => self.y as i64,
+            VAlign::Center => self.y as i64 + (self.h as i64 - ch as i64) / 2,
+            VAlign::Bottom => self.bottom() as i64 - ch as i64,
+        };
+        Bounds::new(x as i32, y as i32, w, ch)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum HAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VAlign {
+    #[default]
+    Top,
+    Center,
+    Bottom,
+}
+
+// Offsets and lengths of `n` cells along a span. If the gaps don't fit, cells are
+// zero-sized and the gaps are kept.
+fn split_span(len: u32, n: u32, gap: u32) -> impl Iterator<Item = (i32, u32)> {
+    let space = len.saturating_sub(gap.saturating_mul(n.saturating_sub(1)));
+    let cell = space.checked_div(n).unwrap_or(0);
+    (0..n).map(move |i| {
+        let offset = (cell as i64 + gap as i64) * i as i64;
+        let size = if i + 1 == n {
+            space - cell * (n - 1)
+        } else {
+            cell
+        };
+        (offset as i32, size)
+    })
+}
+
+/// Returns the first point where the line from `start` to `end` touches the bounds,
+/// rounded to the nearest pixel. A line that starts inside the bounds returns `start`.
+pub fn line_intersects_bounds(
+    start: (i32, i32),
+    end: (i32, i32),
+    bounds: &Bounds,
+) -> Option<(i32, i32)> {
+    if bounds.w == 0 || bounds.h == 0 {
+        return None;
+    }
+    let (x0, y0) = (start.0 as f32, start.1 as f32);
+    let (dx, dy) = ((end.0 - start.0) as f32, (end.1 - start.1) as f32);
+    // Liang-Barsky: narrow the range of t where the line is inside each pair of edges
+    let (mut t_enter, mut t_exit) = (0f32, 1f32);
+    for (p, q) in [
+        (-dx, x0 - bounds.x as f32),
+        (dx, bounds.right() as f32 - x0),
+        (-dy, y0 - bounds.y as f32),
+        (dy, bounds.bottom() as f32 - y0),
+    ] {
+        if p == 0. {
+            // Parallel to this edge and outside it
+            if q < 0. {
+                return None;
+            }
+        } else if p < 0. {
+            t_enter = t_enter.max(q / p);
+        } else {
+            t_exit = t_exit.min(q / p);
+        }
+    }
+    if t_enter > t_exit {
+        return None;
+    }
+    Some((
+        (x0 + dx * t_enter).round() as i32,
+        (y0 + dy * t_enter).round() as i32,
+    ))
+}
+
+/// Straight-line distance between two points.
+pub fn point_distance(a: (i32, i32), b: (i32, i32)) -> f32 {
+    let dx = (b.0 - a.0) as f32;
+    let dy = (b.1 - a.1) as f32;
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Buckets bounds into a grid of square cells so overlap queries only check
+/// nearby entries. Meant to be cleared and refilled each frame.
+#[derive(Debug, Clone)]
+pub struct SpatialHash<Id> {
+    cell_size: u32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    entries: Vec<(Id, Bounds)>,
+}
+
+impl<Id: Copy> Default for SpatialHash<Id> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CELL_SIZE)
+    }
+}
+
+impl<Id: Copy> SpatialHash<Id> {
+    /// Works well when most entries are smaller than a cell
+    pub const DEFAULT_CELL_SIZE: u32 = 64;
+
+    /// Creates an empty hash. A `cell_size` of 0 is treated as 1.
+    pub fn new(cell_size: u32) -> Self {
+        Self {
+            cell_size: cell_size.max(1),
+            cells: HashMap::new(),
+            entries: vec![],
+        }
+    }
+
+    pub fn cell_size(&self) -> u32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all entries, keeping allocations for the next frame
+    pub fn clear(&mut self) {
+        self.cells.values_mut().for_each(Vec::clear);
+        self.entries.clear();
+    }
+
+    pub fn insert(&mut self, id: Id, bounds: Bounds) {
+        let index = self.entries.len();
+        self.entries.push((id, bounds));
+        for cell in self.cells_for(&bounds) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+
+    /// Returns the ids of entries overlapping `bounds`, each once, in insertion order
+    pub fn query(&self, bounds: &Bounds) -> impl Iterator<Item = Id> + '_ {
+        let mut found = self
+            .cells_for(bounds)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+        found.dedup();
+        let bounds = *bounds;
+        found.into_iter().filter_map(move |index| {
+            let (id, entry) = self.entries[index];
+            entry.overlaps(&bounds).then_some(id)
+        })
+    }
+
+    // Cells covered by the bounds. Zero-sized bounds cover the cell they sit in.
+    fn cells_for(&self, bounds: &Bounds) -> impl Iterator<Item = (i32, i32)> {
+        let size = self.cell_size as i32;
+        let cols = bounds.x.div_euclid(size)..=(bounds.right() - 1).max(bounds.x).div_euclid(size);
+        let rows = bounds.y.div_euclid(size)..=(bounds.bottom() - 1).max(bounds.y).div_euclid(size);
+        rows.flat_map(move |row| cols.clone().map(move |col| (col, row)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_horizontal() {
+        let b = Bounds::new(10, 5, 100, 20);
+        let cells = b.split_horizontal(3, 5);
+        assert_eq!(
+            cells,
+            vec![
+                Bounds::new(10, 5, 30, 20),
+                Bounds::new(45, 5, 30, 20),
+                Bounds::new(80, 5, 30, 20),
+            ]
+        );
+        // The extra pixel goes to the last cell, which still ends at the right edge
+        let cells = b.split_vertical(3, 0);
+        assert_eq!(cells[0], Bounds::new(10, 5, 100, 6));
+        assert_eq!(cells[2], Bounds::new(10, 17, 100, 8));
+        assert_eq!(cells[2].bottom(), b.bottom());
+        assert!(b.split_horizontal(0, 5).is_empty());
+    }
+
+    #[test]
+    fn test_align_within() {
+        let b = Bounds::new(0, 0, 100, 50);
+        assert_eq!(
+            b.align_within((20, 10), HAlign::Center, VAlign::Center),
+            Bounds::new(40, 20, 20, 10)
+        );
+        assert_eq!(
+            b.align_within((20, 10), HAlign::Right, VAlign::Bottom),
+            Bounds::new(80, 40, 20, 10)
+        );
+        assert_eq!(
+            b.align_within((120, 10), HAlign::Center, VAlign::Top),
+            Bounds::new(-10, 0, 120, 10)
+        );
+    }
+
+    #[test]
+    fn test_spatial_hash_query() {
+        let mut hash = SpatialHash::new(10);
+        hash.insert(1, Bounds::new(0, 0, 5, 5));
+        // Spans four cells
+        hash.insert(2, Bounds::new(5, 5, 10, 10));
+        hash.insert(3, Bounds::new(-20, -20, 5, 5));
+        let ids = |b: Bounds| hash.query(&b).collect::<Vec<_>>();
+        assert_eq!(ids(Bounds::new(0, 0, 20, 20)), vec![1, 2]);
+        assert_eq!(ids(Bounds::new(12, 12, 2, 2)), vec![2]);
+        // Same cell, but no overlap
+        assert_eq!(ids(Bounds::new(6, 0, 2, 2)), Vec::<i32>::new());
+        assert_eq!(ids(Bounds::new(-18, -18, 1, 1)), vec![3]);
+        hash.clear();
+        assert!(hash.is_empty());
+        assert_eq!(hash.query(&Bounds::new(0, 0, 20, 20)).count(), 0);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let a = Bounds::new(0, 0, 10, 10);
+        assert!(a.overlaps(&Bounds::new(9, 9, 5, 5)));
+        assert!(!a.overlaps(&Bounds::new(10, 0, 5, 5)));
+        assert!(!a.overlaps(&Bounds::new(-5, -5, 5, 20)));
+        assert!(!a.overlaps(&Bounds::new(5, 5, 0, 0)));
+        assert!(a.contains(0, 9) && !a.contains(10, 0));
+    }
+
+    #[test]
+    fn test_line_intersects_bounds() {
+        let b = Bounds::new(10, 10, 10, 10);
+        assert_eq!(
+            line_intersects_bounds((0, 15), (30, 15), &b),
+            Some((10, 15))
+        );
+        assert_eq!(
+            line_intersects_bounds((30, 15), (0, 15), &b),
+            Some((20, 15))
+        );
+        assert_eq!(line_intersects_bounds((0, 0), (30, 30), &b), Some((10, 10)));
+        // Starting inside returns the start
+        assert_eq!(
+            line_intersects_bounds((12, 12), (50, 12), &b),
+            Some((12, 12))
+        );
+        assert_eq!(line_intersects_bounds((0, 0), (5, 5), &b), None);
+        assert_eq!(line_intersects_bounds((0, 5), (30, 5), &b), None);
+        assert_eq!(
+            line_intersects_bounds((15, 15), (15, 15), &b),
+            Some((15, 15))
+        );
+        assert_eq!(point_distance((0, 0), (3, 4)), 5.0);
+    }
+
+    #[test]
+    fn test_grid_and_tiles() {
+        let parent = Bounds::new(5, 10, 10, 4);
+        let cells: Vec<Bounds> = parent.grid(3, 2).collect();
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells[0], Bounds::new(5, 10, 3, 2));
+        assert_eq!(cells[1], Bounds::new(8, 10, 3, 2));
+        assert_eq!(cells[2], Bounds::new(11, 10, 4, 2));
+        assert_eq!(cells[3], Bounds::new(5, 12, 3, 2));
+        assert_eq!(cells.iter().map(|c| c.w * c.h).sum::<u32>(), 40);
+        assert_eq!(parent.grid(0, 2).count(), 0);
+
+        let tiles: Vec<Bounds> = parent.tiles(4, 3).collect();
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[2], Bounds::new(13, 10, 2, 3));
+        assert_eq!(tiles[5], Bounds::new(13, 13, 2, 1));
+    }
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
+use crate::bounds::Bounds;
 use crate::ffi;
+use crate::tween::Tween;
 use borsh::{BorshDeserialize, BorshSerialize};
 
 //------------------------------------------------------------------------------
@@ -48,6 +51,56 @@ macro_rules! clear {
     (@coerce color, $val:expr) => { $val as u32; };
 }
 
+//------------------------------------------------------------------------------
+// Color
+//------------------------------------------------------------------------------
+
+// Colors are packed as 0xRRGGBBAA, with red in the most significant byte
+pub mod color {
+    /// Packs channels into a 0xRRGGBBAA color.
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+        u32::from_be_bytes([r, g, b, a])
+    }
+
+    /// Converts hue (degrees, wrapping), saturation, and value (0 to 1) to an opaque color.
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> u32 {
+        let h = if h.is_finite() {
+            h.rem_euclid(360.0)
+        } else {
+            0.0
+        };
+        let s = if s.is_nan() { 0.0 } else { s.clamp(0.0, 1.0) };
+        let v = if v.is_nan() { 0.0 } else { v.clamp(0.0, 1.0) };
+        let c = v * s;
+        let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+        let m = v - c;
+        // rem_euclid can round up to 360 for tiny negative hues
+        let (r, g, b) = match ((h / 60.0) as u32).min(5) {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let channel = |n: f32| ((n + m) * 255.0).round() as u8;
+        rgba(channel(r), channel(g), channel(b), 0xff)
+    }
+
+    /// Blends each channel, including alpha, from `a` to `b`. `t` is clamped to 0 to 1.
+    pub fn lerp(a: u32, b: u32, t: f32) -> u32 {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let (a, b) = (a.to_be_bytes(), b.to_be_bytes());
+        let channel = |i: usize| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * t).round() as u8;
+        rgba(channel(0), channel(1), channel(2), channel(3))
+    }
+
+    /// Replaces the alpha channel of a color.
+    pub fn with_alpha(color: u32, a: u8) -> u32 {
+        (color & 0xffffff00) | a as u32
+    }
+}
+
 //------------------------------------------------------------------------------
 // Camera
 //------------------------------------------------------------------------------
@@ -75,6 +128,88 @@ pub fn set_camera2(x: f32, y: f32, z: f32) {
     ffi::canvas::set_camera2(x, y, f32::max(z, 0.0));
 }
 
+/// Serializes the camera's position and zoom, e.g. to keep in game state so the
+/// camera doesn't jump after a hot-reload.
+pub fn save_camera_state() -> Vec<u8> {
+    // Same layout as Borsh, which would panic on a NaN instead of saving it
+    let (x, y, z) = get_camera2();
+    [x, y, z].iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// Restores a camera saved with `save_camera_state`. Returns false and leaves the
+/// camera as-is if the bytes aren't a valid camera state.
+pub fn restore_camera_state(bytes: &[u8]) -> bool {
+    match decode_camera_state(bytes) {
+        Some((x, y, z)) => {
+            set_camera2(x, y, z);
+            true
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn decode_camera_state(bytes: &[u8]) -> Option<(f32, f32, f32)> {
+    let (x, y, z) = <(f32, f32, f32)>::try_from_slice(bytes).ok()?;
+    (x.is_finite() && y.is_finite() && z.is_finite()).then_some((x, y, z))
+}
+
+// The area of the world currently visible through the camera
+pub fn viewport() -> Bounds {
+    let (cx, cy, cz) = get_camera2();
+    let zoom = if cz > 0. { cz } else { 1. };
+    let [w, h] = canvas_size();
+    let w = w as f32 / zoom;
+    let h = h as f32 / zoom;
+    Bounds::new(
+        (cx - w / 2.).floor() as i32,
+        (cy - h / 2.).floor() as i32,
+        w.ceil() as u32,
+        h.ceil() as u32,
+    )
+}
+
+// Maps a screen position to the world position drawn there. Also returns the
+// zoom, which sizes must be divided by to keep their screen size.
+pub fn screen_to_world(x: i32, y: i32) -> (i32, i32, f32) {
+    screen_to_world_at(get_camera2(), canvas_size(), x, y)
+}
+
+pub(crate) fn screen_to_world_at(
+    (cx, cy, cz): (f32, f32, f32),
+    [w, h]: [u32; 2],
+    x: i32,
+    y: i32,
+) -> (i32, i32, f32) {
+    let zoom = if cz > 0. { cz } else { 1. };
+    let x = cx + (x as f32 - w as f32 / 2.) / zoom;
+    let y = cy + (y as f32 - h as f32 / 2.) / zoom;
+    (x.floor() as i32, y.floor() as i32, zoom)
+}
+
+/// Zooms the camera to `z` while keeping the world point `x`, `y` under the same
+/// screen pixel, e.g. the point under the cursor. `z` is clamped to 0 or more.
+pub fn zoom_to(x: f32, y: f32, z: f32) {
+    let (cx, cy, cz) = zoom_camera_about(get_camera2(), x, y, z);
+    set_camera2(cx, cy, cz)
+}
+
+// The camera after zooming about a world point. A zoom of 0 draws at 1x.
+pub(crate) fn zoom_camera_about(
+    camera: (f32, f32, f32),
+    x: f32,
+    y: f32,
+    z: f32,
+) -> (f32, f32, f32) {
+    let (cx, cy, cz) = camera;
+    let z = f32::max(z, 0.0);
+    let scale = |z: f32| if z > 0. { z as f64 } else { 1. };
+    // The point's offset from the camera center shrinks as the zoom grows
+    let ratio = scale(cz) / scale(z);
+    let cx = x as f64 - (x as f64 - cx as f64) * ratio;
+    let cy = y as f64 - (y as f64 - cy as f64) * ratio;
+    (cx as f32, cy as f32, z)
+}
+
 #[macro_export]
 macro_rules! cam {
     () => {{
@@ -121,6 +256,250 @@ macro_rules! move_cam {
     (@coerce z, $val:expr) => { $val as f32; };
 }
 
+//------------------------------------------------------------------------------
+// Clip
+//------------------------------------------------------------------------------
+
+// Stack of active clip regions. The top entry is the one applied.
+static mut CLIP_STACK: Vec<Bounds> = Vec::new();
+
+/// Constrains subsequent draws to `bounds`, intersected with the current clip.
+/// Bounds are in world space, so the camera transform applies as with other draws.
+/// Draws queued on a layer keep the clip that was active when they were queued.
+pub fn push_clip(bounds: Bounds) {
+    let stack = unsafe { &mut *std::ptr::addr_of_mut!(CLIP_STACK) };
+    let clip = match stack.last() {
+        Some(parent) => parent.intersection(&bounds),
+        None => bounds,
+    };
+    stack.push(clip);
+    ffi::canvas::set_clip(clip.x, clip.y, clip.w, clip.h);
+}
+
+/// Restores the previous clip region. Does nothing if no clip is active.
+pub fn pop_clip() {
+    let stack = unsafe { &mut *std::ptr::addr_of_mut!(CLIP_STACK) };
+    if stack.pop().is_none() {
+        return;
+    }
+    match stack.last() {
+        Some(clip) => ffi::canvas::set_clip(clip.x, clip.y, clip.w, clip.h),
+        None => ffi::canvas::reset_clip(),
+    }
+}
+
+//------------------------------------------------------------------------------
+// Layers
+//------------------------------------------------------------------------------
+
+// Draws without a layer go in the middle, so others can be placed above or below
+pub const DEFAULT_LAYER: i32 = 0;
+
+#[derive(Debug, Clone)]
+enum DrawCommand {
+    Rect {
+        color: u32,
+        dx: i32,
+        dy: i32,
+        dw: u32,
+        dh: u32,
+        border_radius: u32,
+        border_size: u32,
+        border_color: u32,
+        rotation_deg: i32,
+    },
+    Sprite(SpriteDraw),
+    Text {
+        x: i32,
+        y: i32,
+        font: Font,
+        color: u32,
+        text: String,
+    },
+}
+
+// A queued draw with the layer, clip, and camera it was submitted with
+#[derive(Debug, Clone)]
+struct QueuedDraw {
+    layer: i32,
+    clip: Option<Bounds>,
+    camera: (f32, f32, f32),
+    draw: DrawCommand,
+}
+
+// Draws queued while a frame is open
+struct LayerQueue {
+    active: bool,
+    layer: i32,
+    draws: Vec<QueuedDraw>,
+}
+
+static mut LAYER_QUEUE: LayerQueue = LayerQueue {
+    active: false,
+    layer: DEFAULT_LAYER,
+    draws: Vec::new(),
+};
+
+fn layer_queue() -> &'static mut LayerQueue {
+    unsafe { &mut *std::ptr::addr_of_mut!(LAYER_QUEUE) }
+}
+
+fn current_clip() -> Option<Bounds> {
+    unsafe { (*std::ptr::addr_of!(CLIP_STACK)).last().copied() }
+}
+
+// Queues the draw if a frame is open. Returns false if it should be drawn immediately.
+fn queue_draw(draw: impl FnOnce() -> DrawCommand) -> bool {
+    let queue = layer_queue();
+    if !queue.active {
+        return false;
+    }
+    queue.draws.push(QueuedDraw {
+        layer: queue.layer,
+        clip: current_clip(),
+        camera: get_camera2(),
+        draw: draw(),
+    });
+    true
+}
+
+/// Starts deferring draws until `flush_layers`. Until then, draws are only queued,
+/// while calls like `clear` and camera changes still take effect immediately.
+/// Each queued draw keeps the camera and clip it was made with.
+pub fn begin_layers() {
+    layer_queue().active = true;
+}
+
+/// Draws everything queued since `begin_layers`, ordered by layer and then by the
+/// order the draws were made in.
+pub fn flush_layers() {
+    layer_queue().active = false;
+    draw_queued();
+}
+
+// Draws and clears the queue without closing it. The queue is paused while
+// draining so the flushed draws reach the host instead of being queued again.
+fn draw_queued() {
+    let queue = layer_queue();
+    let active = std::mem::replace(&mut queue.active, false);
+    let draws = std::mem::take(&mut queue.draws);
+    let set_clip = |clip: Option<Bounds>| match clip {
+        Some(clip) => ffi::canvas::set_clip(clip.x, clip.y, clip.w, clip.h),
+        None => ffi::canvas::reset_clip(),
+    };
+    for step in flush_steps(draws, get_camera2(), current_clip()) {
+        match step {
+            FlushStep::Camera((x, y, z)) => set_camera2(x, y, z),
+            FlushStep::Clip(clip) => set_clip(clip),
+            FlushStep::Draw(draw) => draw_command(draw),
+        }
+    }
+    layer_queue().active = active;
+}
+
+#[derive(Debug)]
+enum FlushStep {
+    Camera((f32, f32, f32)),
+    Clip(Option<Bounds>),
+    Draw(DrawCommand),
+}
+
+// Orders queued draws by layer, with the camera and clip changes needed to make
+// each draw as it was submitted. Ends by restoring the current camera and clip.
+fn flush_steps(
+    mut draws: Vec<QueuedDraw>,
+    camera: (f32, f32, f32),
+    clip: Option<Bounds>,
+) -> Vec<FlushStep> {
+    draws.sort_by_key(|draw| draw.layer);
+    let mut steps = vec![];
+    let (mut applied_camera, mut applied_clip) = (camera, clip);
+    for draw in draws {
+        if draw.camera != applied_camera {
+            steps.push(FlushStep::Camera(draw.camera));
+            applied_camera = draw.camera;
+        }
+        if draw.clip != applied_clip {
+            steps.push(FlushStep::Clip(draw.clip));
+            applied_clip = draw.clip;
+        }
+        steps.push(FlushStep::Draw(draw.draw));
+    }
+    if applied_camera != camera {
+        steps.push(FlushStep::Camera(camera));
+    }
+    if applied_clip != clip {
+        steps.push(FlushStep::Clip(clip));
+    }
+    steps
+}
+
+fn draw_command(draw: DrawCommand) {
+    match draw {
+        DrawCommand::Rect {
+            color,
+            dx,
+            dy,
+            dw,
+            dh,
+            border_radius,
+            border_size,
+            border_color,
+            rotation_deg,
+        } => draw_rect(
+            color,
+            dx,
+            dy,
+            dw,
+            dh,
+            border_radius,
+            border_size,
+            border_color,
+            rotation_deg,
+        ),
+        DrawCommand::Sprite(draw) => draw.draw(),
+        DrawCommand::Text {
+            x,
+            y,
+            font,
+            color,
+            text: s,
+        } => text(x, y, font, color, &s),
+    }
+}
+
+/// Sets the layer for draws made while the guard is alive.
+pub struct LayerGuard(i32);
+impl LayerGuard {
+    pub fn new(layer: i32) -> Self {
+        let queue = layer_queue();
+        let prev = queue.layer;
+        queue.layer = layer;
+        Self(prev)
+    }
+}
+impl Drop for LayerGuard {
+    fn drop(&mut self) {
+        layer_queue().layer = self.0;
+    }
+}
+
+/// Opts into layered drawing for a frame. Draws made while it's alive are queued
+/// and drawn by layer when it's dropped, so early returns from a frame still draw.
+/// Without one, draws happen immediately in call order and `layer` is ignored.
+pub struct LayerFrame;
+impl LayerFrame {
+    pub fn begin() -> Self {
+        begin_layers();
+        Self
+    }
+}
+impl Drop for LayerFrame {
+    fn drop(&mut self) {
+        flush_layers();
+    }
+}
+
 //------------------------------------------------------------------------------
 // Sprite
 //------------------------------------------------------------------------------
@@ -140,37 +519,124 @@ pub struct SpriteSourceData {
     pub frames: Vec<(u32, u32)>,
 }
 
-pub fn get_sprite_data(name: &str) -> Option<SpriteSourceData> {
+// Returns a shared handle so per-sprite lookups don't clone the frame data.
+// Handles stay valid after the cache is refreshed for a newer nonce.
+pub fn get_sprite_data(name: &str) -> Option<Rc<SpriteSourceData>> {
     unsafe {
-        static mut SPRITE_DATA: Option<(u64, HashMap<String, SpriteSourceData>)> = None;
+        static mut SPRITE_DATA: Option<(u64, HashMap<String, Rc<SpriteSourceData>>)> = None;
+        static mut SPRITE_DATA_TICK: Option<usize> = None;
         if SPRITE_DATA == None {
             SPRITE_DATA = Some((0, HashMap::new()));
         }
+        // Only check the host for new sprite data once per frame
+        let tick = crate::sys::tick();
+        let checked = SPRITE_DATA_TICK == Some(tick);
+        SPRITE_DATA_TICK = Some(tick);
         let prev_nonce = SPRITE_DATA.as_ref().unwrap().0;
-        let nonce = ffi::canvas::get_sprite_data_nonce_v1();
+        let nonce = if checked {
+            prev_nonce
+        } else {
+            ffi::canvas::get_sprite_data_nonce_v1()
+        };
         if prev_nonce >= nonce {
             return SPRITE_DATA.as_ref().unwrap().1.get(name).cloned();
         }
-        let mut data: [u8; 8192] = [0; 8192]; // up to 8kb sprite data
-        let data_ptr = data.as_mut_ptr();
-        let mut len = data.len() as u32;
-        let len_ptr = &mut len;
-        ffi::canvas::get_sprite_data_v1(data_ptr, len_ptr);
+        // Most projects fit in 8kb. Larger ones are read again at their full size.
+        let (data, len) = read_resized(8192, |buf| {
+            let mut len = buf.len() as u32;
+            ffi::canvas::get_sprite_data_v1(buf.as_mut_ptr(), &mut len);
+            len as usize
+        });
         type SpriteData = Vec<(String, SpriteSourceData)>;
-        let sprite_data = SpriteData::deserialize(&mut &data[..]);
+        let sprite_data = match data.get(..len) {
+            Some(bytes) => SpriteData::deserialize(&mut &bytes[..]),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "sprite data doesn't fit the buffer",
+            )),
+        };
+        // A failed decode still records the nonce so it's only retried (and logged)
+        // once the host has new data
+        SPRITE_DATA.as_mut().unwrap().0 = nonce;
         match sprite_data {
             Ok(data) => {
-                SPRITE_DATA.as_mut().unwrap().0 = nonce;
-                SPRITE_DATA.as_mut().unwrap().1 = data.into_iter().collect();
+                SPRITE_DATA.as_mut().unwrap().1 = data
+                    .into_iter()
+                    .map(|(name, data)| (name, Rc::new(data)))
+                    .collect();
             }
             Err(err) => {
-                crate::println!("Sprite data deserialization failed: {err:?}");
+                crate::println!(
+                    "Sprite data deserialization failed ({len} bytes read, {} byte buffer): {err:?}",
+                    data.len()
+                );
             }
         }
         return SPRITE_DATA.as_ref().unwrap().1.get(name).cloned();
     }
 }
 
+// Reads host data into a buffer of `capacity` bytes. If the reported length is
+// larger, reads once more into a buffer of that size. Returns the buffer and the
+// last reported length, which only exceeds the buffer if the data kept growing.
+pub(crate) fn read_resized(
+    capacity: usize,
+    mut read: impl FnMut(&mut [u8]) -> usize,
+) -> (Vec<u8>, usize) {
+    let mut data = vec![0; capacity];
+    let len = read(&mut data);
+    if len <= data.len() {
+        return (data, len);
+    }
+    data = vec![0; len];
+    let len = read(&mut data);
+    (data, len)
+}
+
+/// The width and height of a single frame of a sprite, or `None` if no sprite has that name
+pub fn sprite_size(name: &str) -> Option<(u32, u32)> {
+    get_sprite_data(name).map(|data| (data.width, data.height))
+}
+
+/// The number of animation frames in a sprite, or `None` if no sprite has that name
+pub fn sprite_frame_count(name: &str) -> Option<usize> {
+    get_sprite_data(name).map(|data| data.frames.len())
+}
+
+/// Checks if a sprite drawn into `bounds` would overlap the viewport, for skipping
+/// off-screen draws. Rotation turns the bounds about `origin`, relative to their
+/// top-left, the same way sprites are drawn. `sprite!` does this with `cull = true`.
+pub fn is_sprite_visible(bounds: Bounds, origin: (i32, i32), rotation_deg: i32) -> bool {
+    rotated_bounds(bounds, origin, rotation_deg).overlaps(&viewport())
+}
+
+// The axis-aligned box around `bounds` rotated about `origin`, relative to its top-left
+pub(crate) fn rotated_bounds(bounds: Bounds, (ox, oy): (i32, i32), rotation_deg: i32) -> Bounds {
+    if rotation_deg % 360 == 0 {
+        return bounds;
+    }
+    let (sin, cos) = (rotation_deg as f32).to_radians().sin_cos();
+    let (px, py) = ((bounds.x + ox) as f32, (bounds.y + oy) as f32);
+    let (w, h) = (bounds.w as f32, bounds.h as f32);
+    let (mut min_x, mut min_y) = (f32::INFINITY, f32::INFINITY);
+    let (mut max_x, mut max_y) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for (x, y) in [(0., 0.), (w, 0.), (0., h), (w, h)] {
+        let (dx, dy) = (x - ox as f32, y - oy as f32);
+        // Rounded so float error at right angles doesn't grow the box by a pixel
+        let x = ((px + dx * cos - dy * sin) * 1e3).round() / 1e3;
+        let y = ((py + dx * sin + dy * cos) * 1e3).round() / 1e3;
+        (min_x, min_y) = (min_x.min(x), min_y.min(y));
+        (max_x, max_y) = (max_x.max(x), max_y.max(y));
+    }
+    let (x, y) = (min_x.floor(), min_y.floor());
+    Bounds::new(
+        x as i32,
+        y as i32,
+        (max_x.ceil() - x) as u32,
+        (max_y.ceil() - y) as u32,
+    )
+}
+
 pub fn draw_sprite(
     dx: i32,
     dy: i32,
@@ -190,27 +656,91 @@ pub fn draw_sprite(
     rotatation_deg: i32,
     flags: u32,
 ) {
-    let dest_xy = ((dx as u64) << 32) | (dy as u64 & 0xffffffff);
-    let dest_wh = ((dw as u64) << 32) | (dh as u32 as u64);
-    let sprite_xy = ((sx as u64) << 32) | (sy as u64);
-    let sprite_xy_offset = ((tx as u64) << 32) | (ty as u32 as u64);
-    let sprite_wh = ((sw as u64) << 32) | (sh as u32 as u64);
-    let origin_xy = ((origin_x as u64) << 32) | (origin_y as u64 & 0xffffffff);
-    let fill_ab = (background_color as u64) << 32 | (color as u64 & 0xffffffff);
-    ffi::canvas::draw_quad2(
-        dest_xy,
-        dest_wh,
-        sprite_xy,
-        sprite_wh,
-        sprite_xy_offset,
-        fill_ab,
+    SpriteDraw {
+        dx,
+        dy,
+        dw,
+        dh,
+        sx,
+        sy,
+        sw,
+        sh,
+        tx,
+        ty,
+        color,
+        background_color,
         border_radius,
-        0,
-        0,
-        origin_xy,
-        rotatation_deg,
+        origin_x,
+        origin_y,
+        rotation_deg: rotatation_deg,
         flags,
-    )
+        tint_color: 0xffffffff,
+        tint_strength: 0.0,
+    }
+    .draw()
+}
+
+// Blends the RGB channels of `color` toward `tint_color`, keeping the alpha of `color`
+pub(crate) fn tint(color: u32, tint_color: u32, strength: f32) -> u32 {
+    if strength.is_nan() || strength <= 0.0 {
+        return color;
+    }
+    let strength = strength.min(1.0);
+    let channel = |shift: u32| {
+        let from = ((color >> shift) & 0xff) as f32;
+        let to = ((tint_color >> shift) & 0xff) as f32;
+        ((from + (to - from) * strength).round() as u32) << shift
+    };
+    channel(24) | channel(16) | channel(8) | (color & 0xff)
+}
+
+// Multiplies the alpha channel of an RGBA color by a gamma-corrected opacity
+pub fn apply_opacity(color: u32, opacity: f32) -> u32 {
+    if opacity == 1.0 {
+        return color;
+    }
+    // Apply gamma correction
+    let gamma = 2.2;
+    let linear_opacity = opacity.clamp(0.0, 1.0).powf(1.0 / gamma);
+
+    // Calculate the alpha value
+    let alpha = ((color & 0xff) as f32 * linear_opacity) as u32;
+
+    // Combine the alpha with the color
+    alpha | (color & 0xffffff00)
+}
+
+// Mirrors a destination span about its origin when flipped. Returns the new
+// start and the origin relative to it. Without an origin, the span is flipped
+// in place, which is the same as mirroring about its center.
+pub fn flip_about_origin(flip: bool, x: i32, len: u32, origin: Option<i32>) -> (i32, i32) {
+    match origin {
+        Some(origin) if flip => {
+            let next_x = x + 2 * origin - len as i32;
+            (next_x, x + origin - next_x)
+        }
+        origin => (x, origin.unwrap_or(0)),
+    }
+}
+
+// Wraps a texture offset to the source size. Flooring instead of truncating keeps
+// the step between pixels even as fractional offsets cross zero.
+pub fn scroll_offset(offset: f32, size: u32) -> i32 {
+    if size == 0 || !offset.is_finite() {
+        return 0;
+    }
+    // rem_euclid can round up to `size` for tiny negative offsets
+    (offset.rem_euclid(size as f32).floor() as u32 % size) as i32
+}
+
+// Splits a repeated sprite's destination into tiles along its local axes.
+// Returns each tile's offset from the top-left corner and its size. Tiles on
+// the right and bottom edges are cropped to fit.
+pub fn repeat_tiles(dw: u32, dh: u32, tile_w: u32, tile_h: u32) -> Vec<(i32, i32, u32, u32)> {
+    Bounds::new(0, 0, dw, dh)
+        .tiles(tile_w, tile_h)
+        .map(|tile| (tile.x, tile.y, tile.w, tile.h))
+        .collect()
 }
 
 pub mod flags {
@@ -218,8 +748,148 @@ pub mod flags {
     pub const SPRITE_REPEAT: u32 = 1 << 0;
     // Scales a sprite to fit the dimensions of the containing quad
     pub const SPRITE_COVER: u32 = 2 << 0;
+    // Adds the sprite's color to the destination, scaled by its alpha
+    pub const SPRITE_BLEND_ADDITIVE: u32 = 1 << 2;
+    // Multiplies the destination by the sprite's color
+    pub const SPRITE_BLEND_MULTIPLY: u32 = 1 << 3;
+}
+
+/// How a sprite is blended onto what's under it. Released hosts only honor
+/// `Normal`, so the other modes draw as `Normal` without `unstable-host`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Additive,
+    Multiply,
+}
+impl BlendMode {
+    /// Gets the sprite flag bits for this blend mode.
+    pub fn flags(self) -> u32 {
+        if !cfg!(feature = "unstable-host") {
+            return 0;
+        }
+        match self {
+            Self::Normal => 0,
+            Self::Additive => flags::SPRITE_BLEND_ADDITIVE,
+            Self::Multiply => flags::SPRITE_BLEND_MULTIPLY,
+        }
+    }
 }
 
+/// How a sprite's source is mapped onto its destination size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FitMode {
+    /// Scales each axis independently to fill the destination
+    Stretch,
+    /// Keeps the aspect ratio and fills the destination, cropping the source
+    Cover,
+    /// Keeps the aspect ratio and fits within the destination, leaving empty space
+    Contain,
+}
+impl FitMode {
+    /// Returns the part of the source to draw and the part of the destination to
+    /// draw it into, each relative to the top-left corner. Crops and letterboxing
+    /// are centered.
+    pub fn apply(self, sw: u32, sh: u32, dw: u32, dh: u32) -> (Bounds, Bounds) {
+        let source = Bounds::new(0, 0, sw, sh);
+        let dest = Bounds::new(0, 0, dw, dh);
+        if sw == 0 || sh == 0 || dw == 0 || dh == 0 {
+            return (source, dest);
+        }
+        // Compare aspect ratios without dividing
+        let (sw64, sh64, dw64, dh64) = (sw as u64, sh as u64, dw as u64, dh as u64);
+        let source_wider = sw64 * dh64 > dw64 * sh64;
+        let centered = |outer: u32, inner: u32| ((outer - inner) / 2) as i32;
+        match self {
+            Self::Stretch => (source, dest),
+            Self::Cover if source_wider => {
+                let w = ((sh64 * dw64 + dh64 / 2) / dh64).clamp(1, sw64) as u32;
+                (Bounds::new(centered(sw, w), 0, w, sh), dest)
+            }
+            Self::Cover => {
+                let h = ((sw64 * dh64 + dw64 / 2) / dw64).clamp(1, sh64) as u32;
+                (Bounds::new(0, centered(sh, h), sw, h), dest)
+            }
+            Self::Contain if source_wider => {
+                let h = ((dw64 * sh64 + sw64 / 2) / sw64).clamp(1, dh64) as u32;
+                (source, Bounds::new(0, centered(dh, h), dw, h))
+            }
+            Self::Contain => {
+                let w = ((dh64 * sw64 + sh64 / 2) / sh64).clamp(1, dw64) as u32;
+                (source, Bounds::new(centered(dw, w), 0, w, dh))
+            }
+        }
+    }
+}
+
+/// A position, rotation, and scale that can be composed, so children can be
+/// drawn relative to a parent. Rotation is in degrees, clockwise on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform2D {
+    pub x: f32,
+    pub y: f32,
+    pub rotation: f32,
+    pub scale_x: f32,
+    pub scale_y: f32,
+}
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            rotation: 0.,
+            scale_x: 1.,
+            scale_y: 1.,
+        }
+    }
+}
+impl Transform2D {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            x,
+            y,
+            ..Self::default()
+        }
+    }
+
+    pub fn rotated(self, rotation: f32) -> Self {
+        Self { rotation, ..self }
+    }
+
+    pub fn scaled(self, scale_x: f32, scale_y: f32) -> Self {
+        Self {
+            scale_x,
+            scale_y,
+            ..self
+        }
+    }
+
+    /// Maps a point from local space, scaling, then rotating, then translating it
+    pub fn apply(&self, x: f32, y: f32) -> (f32, f32) {
+        let (x, y) = (x * self.scale_x, y * self.scale_y);
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        (self.x + x * cos - y * sin, self.y + x * sin + y * cos)
+    }
+
+    /// Places `child`, given relative to this transform, in this transform's
+    /// parent space. Scales multiply, which is exact unless a rotated child sits
+    /// inside a non-uniform scale.
+    pub fn compose(&self, child: &Transform2D) -> Transform2D {
+        let (x, y) = self.apply(child.x, child.y);
+        Transform2D {
+            x,
+            y,
+            rotation: self.rotation + child.rotation,
+            scale_x: self.scale_x * child.scale_x,
+            scale_y: self.scale_y * child.scale_y,
+        }
+    }
+}
+
+/// Draws a sprite and returns the destination bounds it drew into, after
+/// scaling and absolute positioning but before rotation. Returns empty bounds
+/// if the sprite isn't loaded.
 #[macro_export]
 macro_rules! sprite {
     ($name:expr) => {{
@@ -234,18 +904,26 @@ macro_rules! sprite {
             let mut sh: u32 = 0;
             let mut sx: u32 = 0;
             let mut sy: u32 = 0;
+            let mut source_rect: Option<(u32, u32, u32, u32)> = None;
             let mut tx: i32 = 0;
             let mut ty: i32 = 0;
+            let mut scroll_x: f32 = 0.0;
+            let mut scroll_y: f32 = 0.0;
             let mut x: i32 = 0;
             let mut y: i32 = 0;
             let mut w: u32 = u32::MAX;
             let mut h: u32 = u32::MAX;
+            let mut bounds: Option<$crate::bounds::Bounds> = None;
+            let mut transform: Option<$crate::canvas::Transform2D> = None;
             let mut color: u32 = 0xffffffff;
             let mut background_color: u32 = 0x00000000;
             let mut border_radius: u32 = 0;
             let mut opacity: f32 = 1.0;
-            let mut origin_x: i32 = 0;
-            let mut origin_y: i32 = 0;
+            let mut opacity_tween: Option<&$crate::tween::Tween<f32>> = None;
+            let mut x_tween: Option<&$crate::tween::Tween<i32>> = None;
+            let mut y_tween: Option<&$crate::tween::Tween<i32>> = None;
+            let mut origin_x: Option<i32> = None;
+            let mut origin_y: Option<i32> = None;
             let mut rotate: i32 = 0;
             let mut scale: f32 = 1.0;
             let mut scale_x: f32 = 1.0;
@@ -255,7 +933,45 @@ macro_rules! sprite {
             let mut fps: u32 = 0;
             let mut repeat: bool = false;
             let mut absolute: bool = false;
+            let mut fixed: bool = false;
+            let mut cull: bool = false;
+            let mut fit: Option<$crate::canvas::FitMode> = None;
+            let mut blend: $crate::canvas::BlendMode = $crate::canvas::BlendMode::Normal;
+            let mut tint: u32 = 0xffffffff;
+            let mut tint_strength: f32 = 0.0;
+            let mut shadow_x: i32 = 0;
+            let mut shadow_y: i32 = 0;
+            let mut shadow_squash: f32 = 1.0;
+            let mut shadow_color: Option<u32> = None;
+            let mut batch: Option<&mut $crate::canvas::Batch> = None;
+            let mut layer: Option<i32> = None;
             $($crate::paste::paste!{ [< $key >] = sprite!(@coerce $key, $val); })*
+            let _layer = layer.map($crate::canvas::LayerGuard::new);
+
+            // Bounds set the position and size together
+            if let Some(b) = bounds { x = b.x; y = b.y; w = b.w; h = b.h; }
+
+            // Tweens are sampled without advancing, so they can drive several sprites
+            if let Some(t) = x_tween { x = t.sample(); }
+            if let Some(t) = y_tween { y = t.sample(); }
+            if let Some(t) = opacity_tween { opacity = t.sample(); }
+
+            // A transform puts the sprite's origin at its position, replacing the
+            // position, rotation, and scale keys
+            if let Some(t) = transform {
+                let (ox, oy) = (origin_x.unwrap_or(0), origin_y.unwrap_or(0));
+                x = (t.x - ox as f32 * t.scale_x.abs()).round() as i32;
+                y = (t.y - oy as f32 * t.scale_y.abs()).round() as i32;
+                origin_x = Some(ox);
+                origin_y = Some(oy);
+                rotate = t.rotation.round() as i32;
+                scale = 1.0;
+                scale_x = t.scale_x;
+                scale_y = t.scale_y;
+            }
+
+            // A source rect replaces the slice and skips frame offsets
+            if let Some((rx, ry, rw, rh)) = source_rect { sx = rx; sy = ry; sw = rw; sh = rh; }
 
             // Absolute positioning
             if absolute {
@@ -265,24 +981,26 @@ macro_rules! sprite {
                 y += cy - (h as i32 / 2);
             }
 
+            // Fixed positioning uses screen pixels, undoing camera zoom as well
+            if fixed {
+                let (fx, fy, zoom) = $crate::canvas::screen_to_world(x, y);
+                x = fx;
+                y = fy;
+                scale /= zoom;
+            }
+
             // Initialize flags
             let mut flags: u32 = 0;
 
             // Sprite repeat
             if repeat { flags |= $crate::canvas::flags::SPRITE_REPEAT; }
 
-            // Set opacity
-            if opacity != 1.0 {
-                // Apply gamma correction
-                let gamma = 2.2;
-                let linear_opacity = opacity.powf(1.0 / gamma);
+            // Blend mode
+            flags |= blend.flags();
 
-                // Calculate the alpha value
-                let alpha = (255.0 * linear_opacity) as u32;
-
-                // Combine the alpha with the color
-                color = alpha << 32 | (color & 0xffffff00);
-            }
+            // Set opacity. The shadow fades along with the sprite.
+            color = $crate::canvas::apply_opacity(color, opacity);
+            let shadow = shadow_color.map(|c| ($crate::canvas::apply_opacity(c, opacity), shadow_x, shadow_y, shadow_squash));
 
             // If no slice width is given and sprite is not to be drawn animated, multiply width by frames count
             let animated = fps > 0;
@@ -294,6 +1012,10 @@ macro_rules! sprite {
             let sw = if sw == 0 { default_sw - sx } else { sw };
             let sh = if sh == 0 { default_sh - sy } else { sh };
 
+            // Scroll the texture, wrapping at the source size
+            if scroll_x != 0.0 { tx = $crate::canvas::scroll_offset(tx as f32 + scroll_x, sw); }
+            if scroll_y != 0.0 { ty = $crate::canvas::scroll_offset(ty as f32 + scroll_y, sh); }
+
             // Set destination size
             let dw = if w == u32::MAX { sw } else { w };
             let dh = if h == u32::MAX { sh } else { h };
@@ -302,6 +1024,10 @@ macro_rules! sprite {
             scale_x *= scale;
             scale_y *= scale;
 
+            // A negative scale is a flip about the origin
+            if scale_x < 0. { flip_x = !flip_x; scale_x = -scale_x; }
+            if scale_y < 0. { flip_y = !flip_y; scale_y = -scale_y; }
+
             // Set the cover flag if scaling is used
             if scale_x != 1. || scale_y != 1. { flags |= $crate::canvas::flags::SPRITE_COVER; }
 
@@ -309,16 +1035,51 @@ macro_rules! sprite {
             let dw = (dw as f32 * scale_x) as u32;
             let dh = (dh as f32 * scale_y) as u32;
 
+            // Fit the source to the destination. Animated sprites are drawn at their frame size.
+            let (sx, sy, sw, sh, x, y, dw, dh) = match fit.filter(|_| !animated) {
+                Some(fit) => {
+                    flags |= $crate::canvas::flags::SPRITE_COVER;
+                    let (src, dst) = fit.apply(sw, sh, dw, dh);
+                    (sx + src.x as u32, sy + src.y as u32, src.w, src.h, x + dst.x, y + dst.y, dst.w, dst.h)
+                }
+                None => (sx, sy, sw, sh, x, y, dw, dh),
+            };
+
             // Flip sprite
             let sw = if flip_x { -(sw as i32) } else { sw  as i32 };
             let sh = if flip_y { -(sh as i32) } else { sh  as i32 };
 
             // Set transform origin
-            let origin_x = ((origin_x as f32) * scale_x) as i32;
-            let origin_y = ((origin_y as f32) * scale_y) as i32;
-
+            let origin_x = origin_x.map(|origin_x| ((origin_x as f32) * scale_x) as i32);
+            let origin_y = origin_y.map(|origin_y| ((origin_y as f32) * scale_y) as i32);
+
+            // Skip sprites outside the viewport. Absolute and fixed sprites follow the camera.
+            if cull && !absolute && !fixed && !$crate::canvas::is_sprite_visible(
+                $crate::bounds::Bounds::new(x, y, dw, dh),
+                (origin_x.unwrap_or(0), origin_y.unwrap_or(0)),
+                rotate,
+            ) {
+                $crate::bounds::Bounds::new(x, y, dw, dh)
+            }
+            // Draw an exact region of the texture, ignoring frames and animation
+            else if source_rect.is_some() {
+                let (x, origin_x) = $crate::canvas::flip_about_origin(flip_x, x, dw, origin_x);
+                let (y, origin_y) = $crate::canvas::flip_about_origin(flip_y, y, dh, origin_y);
+                let draw = $crate::canvas::SpriteDraw {
+                    dx: x, dy: y, dw, dh,
+                    sx, sy, sw, sh, tx, ty,
+                    color, background_color,
+                    border_radius,
+                    origin_x, origin_y,
+                    rotation_deg: rotate,
+                    flags,
+                    tint_color: tint, tint_strength
+                };
+                draw.submit($name, shadow, batch.as_deref_mut());
+                $crate::bounds::Bounds::new(x, y, dw, dh)
+            }
             // Draw each frame at specified FPS
-            if fps > 0 {
+            else if fps > 0 {
                 let (w, h) = (sprite_data.width, sprite_data.height);
                 let frames_len = if h > 0 && w % h == 0 && w / h > 1 {
                     w / h
@@ -335,25 +1096,59 @@ macro_rules! sprite {
                 } else {
                     frames_len
                 };
-                let frame_rate = (60_usize).checked_div(fps as usize).unwrap_or(1);
+                let frame_rate = ($crate::sys::time::frame_rate() as usize).checked_div(fps as usize).unwrap_or(1);
                 let i = $crate::sys::tick().checked_div(frame_rate).unwrap_or(0) % frames_len as usize;
                 let (fx, fy) = sprite_data.frames[0];
                 let fx = fx + (abs_sw * i as u32);
                 let sx = sx + fx;
                 let sy = sy + fy;
 
-                $crate::canvas::draw_sprite(
-                    x, y, abs_sw, sh.abs() as u32,
+                // Mirror the destination about the origin
+                let (x, origin_x) = $crate::canvas::flip_about_origin(flip_x, x, abs_sw, origin_x);
+                let (y, origin_y) = $crate::canvas::flip_about_origin(flip_y, y, sh.unsigned_abs(), origin_y);
+
+                let draw = $crate::canvas::SpriteDraw {
+                    dx: x, dy: y, dw: abs_sw, dh: sh.abs() as u32,
                     sx, sy, sw, sh, tx, ty,
                     color, background_color,
                     border_radius,
                     origin_x, origin_y,
-                    rotate,
-                    flags
-                );
+                    rotation_deg: rotate,
+                    flags,
+                    tint_color: tint, tint_strength
+                };
+                draw.submit($name, shadow, batch.as_deref_mut());
+                $crate::bounds::Bounds::new(x, y, abs_sw, sh.unsigned_abs())
+            }
+            // Repeat rotated sprites along their local axes. Each tile pivots about
+            // the sprite's origin, so the pattern rotates as one piece.
+            else if repeat && rotate % 360 != 0 {
+                let (x, origin_x) = $crate::canvas::flip_about_origin(flip_x, x, dw, origin_x);
+                let (y, origin_y) = $crate::canvas::flip_about_origin(flip_y, y, dh, origin_y);
+                let (fx, fy) = sprite_data.frames[0];
+                let tile_w = ((sw.unsigned_abs() as f32 * scale_x) as u32).max(1);
+                let tile_h = ((sh.unsigned_abs() as f32 * scale_y) as u32).max(1);
+                for (ox, oy, tw, th) in $crate::canvas::repeat_tiles(dw, dh, tile_w, tile_h) {
+                    let draw = $crate::canvas::SpriteDraw {
+                        dx: x + ox, dy: y + oy, dw: tw, dh: th,
+                        sx: sx + fx, sy: sy + fy, sw, sh, tx, ty,
+                        color, background_color,
+                        border_radius,
+                        origin_x: origin_x - ox, origin_y: origin_y - oy,
+                        rotation_deg: rotate,
+                        flags,
+                        tint_color: tint, tint_strength
+                    };
+                    draw.submit($name, shadow, batch.as_deref_mut());
+                }
+                $crate::bounds::Bounds::new(x, y, dw, dh)
             }
             // Draw all frames as one image
             else {
+                // Mirror the destination about the origin
+                let (x, origin_x) = $crate::canvas::flip_about_origin(flip_x, x, dw, origin_x);
+                let (y, origin_y) = $crate::canvas::flip_about_origin(flip_y, y, dh, origin_y);
+
                 let mut cx = sx;
                 let mut rem_sw = sw.abs() as u32;
                 for i in 0..num_frames {
@@ -388,20 +1183,25 @@ macro_rules! sprite {
                     let dy = (y as f32 + dy) as i32;
 
                     // Draw
-                    $crate::canvas::draw_sprite(
+                    let draw = $crate::canvas::SpriteDraw {
                         dx, dy, dw, dh,
                         sx, sy, sw, sh, tx, ty,
                         color, background_color,
                         border_radius,
                         origin_x, origin_y,
-                        rotate,
-                        flags
-                    );
+                        rotation_deg: rotate,
+                        flags,
+                        tint_color: tint, tint_strength
+                    };
+                    draw.submit($name, shadow, batch.as_deref_mut());
 
                     // Stop drawing if width has been reached
                     if rem_sw == 0 { break; }
                 }
-            };
+                $crate::bounds::Bounds::new(x, y, dw, dh)
+            }
+        } else {
+            $crate::bounds::Bounds::default()
         }
     }};
     // Parent quad position and size. Crops the inner sprite slice
@@ -409,28 +1209,53 @@ macro_rules! sprite {
     (@coerce y, $val:expr) => { $val as i32; };
     (@coerce w, $val:expr) => { $val as u32; };
     (@coerce h, $val:expr) => { $val as u32; };
+    (@coerce bounds, $val:expr) => { Some($val as $crate::bounds::Bounds); };
+    // Absolute offsets by the camera position but still zooms. Fixed pins the
+    // sprite to the screen, so 0, 0 is the top-left at any camera position or zoom.
     (@coerce absolute, $val:expr) => { $val as bool; };
+    (@coerce fixed, $val:expr) => { $val as bool; };
+    // Skips the draw if it's outside the viewport. Absolute and fixed sprites always draw.
+    (@coerce cull, $val:expr) => { $val as bool; };
+    // Maps the source onto w and h. Without it, scaling stretches the sprite.
+    (@coerce fit, $val:expr) => { Some($val as $crate::canvas::FitMode); };
 
     // Sprite slice position and size relative to spritesheet
     (@coerce sx, $val:expr) => { $val as u32; };
     (@coerce sy, $val:expr) => { $val as u32; };
     (@coerce sw, $val:expr) => { $val as u32; };
     (@coerce sh, $val:expr) => { $val as u32; };
+    (@coerce source_rect, $val:expr) => { Some($val); };
 
     // Sprite slice translation
     (@coerce tx, $val:expr) => { $val as i32; };
     (@coerce ty, $val:expr) => { $val as i32; };
+    (@coerce scroll_x, $val:expr) => { $val as f32; };
+    (@coerce scroll_y, $val:expr) => { $val as f32; };
     (@coerce repeat, $val:expr) => { $val as bool; };
 
     (@coerce color, $val:expr) => { $val as u32; };
     (@coerce background_color, $val:expr) => { $val as u32; };
     (@coerce border_radius, $val:expr) => { $val as u32; };
     (@coerce opacity, $val:expr) => { $val as f32; };
+    // Tweens that set opacity and position, sampled without advancing them
+    (@coerce opacity_tween, $val:expr) => { Some($val as &$crate::tween::Tween<f32>); };
+    (@coerce x_tween, $val:expr) => { Some($val as &$crate::tween::Tween<i32>); };
+    (@coerce y_tween, $val:expr) => { Some($val as &$crate::tween::Tween<i32>); };
+    (@coerce blend, $val:expr) => { $val as $crate::canvas::BlendMode; };
+    (@coerce tint, $val:expr) => { $val as u32; };
+    (@coerce tint_strength, $val:expr) => { $val as f32; };
+    (@coerce batch, $val:expr) => { Some($val); };
+    // A drop shadow in a flat color, drawn first. Squash flattens it toward the bottom.
+    (@coerce shadow_color, $val:expr) => { Some($val as u32); };
+    (@coerce shadow_x, $val:expr) => { $val as i32; };
+    (@coerce shadow_y, $val:expr) => { $val as i32; };
+    (@coerce shadow_squash, $val:expr) => { $val as f32; };
 
     // Transforms
-    (@coerce origin_x, $val:expr) => { $val as i32; };
-    (@coerce origin_y, $val:expr) => { $val as i32; };
+    (@coerce origin_x, $val:expr) => { Some($val as i32); };
+    (@coerce origin_y, $val:expr) => { Some($val as i32); };
     (@coerce rotate, $val:expr) => { $val as i32; };
+    (@coerce transform, $val:expr) => { Some($val as $crate::canvas::Transform2D); };
     (@coerce scale, $val:expr) => { $val as f32; };
     (@coerce scale_x, $val:expr) => { $val as f32; };
     (@coerce scale_y, $val:expr) => { $val as f32; };
@@ -439,12 +1264,308 @@ macro_rules! sprite {
 
     // Animation
     (@coerce fps, $val:expr) => { $val as u32; };
+
+    // Draw order
+    (@coerce layer, $val:expr) => { Some($val as i32); };
+}
+
+//------------------------------------------------------------------------------
+// Batch
+//------------------------------------------------------------------------------
+
+// A single sprite quad, as passed to draw_sprite plus a tint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteDraw {
+    pub dx: i32,
+    pub dy: i32,
+    pub dw: u32,
+    pub dh: u32,
+    pub sx: u32,
+    pub sy: u32,
+    pub sw: i32,
+    pub sh: i32,
+    pub tx: i32,
+    pub ty: i32,
+    pub color: u32,
+    pub background_color: u32,
+    pub border_radius: u32,
+    pub origin_x: i32,
+    pub origin_y: i32,
+    pub rotation_deg: i32,
+    pub flags: u32,
+    pub tint_color: u32,
+    pub tint_strength: f32,
+}
+impl SpriteDraw {
+    /// Draws the quad, or queues it if a `LayerFrame` is open. A tint blends the
+    /// texture toward `tint_color` by `tint_strength`, keeping its alpha, so 1.0
+    /// draws a solid silhouette.
+    pub fn draw(&self) {
+        if queue_draw(|| DrawCommand::Sprite(*self)) {
+            return;
+        }
+        let dest_xy = ((self.dx as u64) << 32) | (self.dy as u64 & 0xffffffff);
+        let dest_wh = ((self.dw as u64) << 32) | (self.dh as u64);
+        let sprite_xy = ((self.sx as u64) << 32) | (self.sy as u64);
+        let sprite_xy_offset = ((self.tx as u64) << 32) | (self.ty as u32 as u64);
+        let sprite_wh = ((self.sw as u64) << 32) | (self.sh as u32 as u64);
+        let origin_xy = ((self.origin_x as u64) << 32) | (self.origin_y as u64 & 0xffffffff);
+        let tint_strength = if self.tint_strength.is_nan() {
+            0.0
+        } else {
+            self.tint_strength.clamp(0.0, 1.0)
+        };
+        if cfg!(feature = "unstable-host") && tint_strength > 0.0 {
+            let fill_ab = (self.background_color as u64) << 32 | (self.color as u64);
+            return ffi::canvas::draw_quad3(
+                dest_xy,
+                dest_wh,
+                sprite_xy,
+                sprite_wh,
+                sprite_xy_offset,
+                fill_ab,
+                self.border_radius,
+                0,
+                0,
+                origin_xy,
+                self.rotation_deg,
+                self.flags,
+                self.tint_color,
+                tint_strength,
+            );
+        }
+        // Without the tint parameter, the closest match is tinting the color that
+        // multiplies the texture, which can darken it but not brighten it
+        let color = tint(self.color, self.tint_color, tint_strength);
+        let fill_ab = (self.background_color as u64) << 32 | (color as u64);
+        ffi::canvas::draw_quad2(
+            dest_xy,
+            dest_wh,
+            sprite_xy,
+            sprite_wh,
+            sprite_xy_offset,
+            fill_ab,
+            self.border_radius,
+            0,
+            0,
+            origin_xy,
+            self.rotation_deg,
+            self.flags,
+        )
+    }
+
+    // Destination bounds, or None if rotated since those aren't axis-aligned
+    fn bounds(&self) -> Option<Bounds> {
+        if self.rotation_deg % 360 != 0 {
+            return None;
+        }
+        Some(Bounds::new(self.dx, self.dy, self.dw, self.dh))
+    }
+
+    fn overlaps(&self, other: &SpriteDraw) -> bool {
+        match (self.bounds(), other.bounds()) {
+            (Some(a), Some(b)) => a.overlaps(&b),
+            // Assume rotated sprites overlap everything
+            _ => true,
+        }
+    }
+
+    /// Sets the draw's alpha from the tween's current value, from 0.0 to 1.0. The
+    /// tween is sampled, not advanced, so it can drive other draws.
+    pub fn apply_tween_opacity(&mut self, t: &Tween<f32>) {
+        let alpha = (t.sample().clamp(0.0, 1.0) * 255.0).round() as u32;
+        self.color = (self.color & 0xffffff00) | alpha;
+    }
+
+    /// Moves the draw to the tweens' current position without advancing them.
+    pub fn apply_tween_position(&mut self, x: &Tween<i32>, y: &Tween<i32>) {
+        self.dx = x.sample();
+        self.dy = y.sample();
+    }
+
+    /// A flat-colored copy of this draw for a drop shadow. The sprite's alpha is
+    /// the shadow's shape, and `squash` flattens it toward the sprite's bottom edge
+    /// before it's moved by the offset.
+    pub fn shadow(&self, color: u32, offset_x: i32, offset_y: i32, squash: f32) -> SpriteDraw {
+        let squash = if squash.is_nan() { 1. } else { squash.max(0.) };
+        let dh = (self.dh as f32 * squash).round() as u32;
+        let mut flags = self.flags;
+        if dh != self.dh {
+            flags |= flags::SPRITE_COVER;
+        }
+        SpriteDraw {
+            dx: self.dx + offset_x,
+            dy: self.dy + (self.dh as i32 - dh as i32) + offset_y,
+            dh,
+            // Only the alpha of the color is kept, so the tint sets the shadow color
+            color: 0xffffff00 | (color & 0xff),
+            background_color: 0,
+            origin_y: (self.origin_y as f32 * squash).round() as i32,
+            flags,
+            tint_color: color | 0xff,
+            tint_strength: 1.,
+            ..*self
+        }
+    }
+
+    // Draws now or adds to the batch, with the shadow drawn first
+    pub fn submit(
+        &self,
+        texture: &str,
+        shadow: Option<(u32, i32, i32, f32)>,
+        batch: Option<&mut Batch>,
+    ) {
+        let shadow = shadow.map(|(color, x, y, squash)| self.shadow(color, x, y, squash));
+        match batch {
+            Some(batch) => {
+                if let Some(shadow) = shadow {
+                    batch.add(texture, shadow);
+                }
+                batch.add(texture, *self);
+            }
+            None => {
+                if let Some(shadow) = shadow {
+                    shadow.draw();
+                }
+                self.draw();
+            }
+        }
+    }
+}
+
+/// Collects sprite draws and flushes them grouped by texture. A draw is only moved
+/// ahead of earlier draws it doesn't overlap, so overlapping sprites keep their order.
+/// Draws are discarded if the batch is dropped without calling `flush`.
+#[derive(Debug, Default, Clone)]
+pub struct Batch {
+    groups: Vec<(String, Vec<SpriteDraw>)>,
+}
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, texture: &str, draw: SpriteDraw) {
+        // Join the latest group for this texture unless that would skip over an overlapping draw
+        let mut target = None;
+        for (i, (key, draws)) in self.groups.iter().enumerate().rev() {
+            if key == texture {
+                target = Some(i);
+                break;
+            }
+            if draws.iter().any(|prev| prev.overlaps(&draw)) {
+                break;
+            }
+        }
+        match target {
+            Some(i) => self.groups[i].1.push(draw),
+            None => self.groups.push((texture.to_string(), vec![draw])),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(|(_, draws)| draws.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Draws everything in the batch and empties it.
+    pub fn flush(&mut self) {
+        for (_, draws) in self.groups.drain(..) {
+            for draw in draws {
+                draw.draw();
+            }
+        }
+    }
 }
 
 //------------------------------------------------------------------------------
 // 9 Slice
 //------------------------------------------------------------------------------
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NineSliceRegion {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+impl NineSliceRegion {
+    pub const ALL: [Self; 9] = [
+        Self::TopLeft,
+        Self::Top,
+        Self::TopRight,
+        Self::Left,
+        Self::Center,
+        Self::Right,
+        Self::BottomLeft,
+        Self::Bottom,
+        Self::BottomRight,
+    ];
+}
+
+// Destination bounds (relative to the panel) and sprite source bounds for each region.
+// Corners are drawn at `dest_slice_size`, or smaller if the panel can't fit two of them.
+pub fn nine_slice_regions(
+    w: u32,
+    h: u32,
+    slice_size: u32,
+    dest_slice_size: u32,
+) -> [(NineSliceRegion, Bounds, Bounds); 9] {
+    let s = slice_size;
+    let corner_w = dest_slice_size.min(w / 2);
+    let corner_h = dest_slice_size.min(h / 2);
+    let inner_w = w - corner_w * 2;
+    let inner_h = h - corner_h * 2;
+    NineSliceRegion::ALL.map(|region| {
+        // Column and row of the region in the 3x3 grid
+        let (col, row) = match region {
+            NineSliceRegion::TopLeft => (0, 0),
+            NineSliceRegion::Top => (1, 0),
+            NineSliceRegion::TopRight => (2, 0),
+            NineSliceRegion::Left => (0, 1),
+            NineSliceRegion::Center => (1, 1),
+            NineSliceRegion::Right => (2, 1),
+            NineSliceRegion::BottomLeft => (0, 2),
+            NineSliceRegion::Bottom => (1, 2),
+            NineSliceRegion::BottomRight => (2, 2),
+        };
+        let (x, w) = [
+            (0, corner_w),
+            (corner_w, inner_w),
+            (corner_w + inner_w, corner_w),
+        ][col];
+        let (y, h) = [
+            (0, corner_h),
+            (corner_h, inner_h),
+            (corner_h + inner_h, corner_h),
+        ][row];
+        let dest = Bounds::new(x as i32, y as i32, w, h);
+        let source = Bounds::new((s * col as u32) as i32, (s * row as u32) as i32, s, s);
+        (region, dest, source)
+    })
+}
+
+// Scales a region's offset within the panel and points its transform origin at the
+// panel's origin so every region rotates and scales as one piece
+pub fn nine_slice_transform(
+    dest: Bounds,
+    origin_x: i32,
+    origin_y: i32,
+    scale: f32,
+) -> (i32, i32, i32, i32) {
+    let x = (dest.x as f32 * scale).round() as i32;
+    let y = (dest.y as f32 * scale).round() as i32;
+    (x, y, origin_x - dest.x, origin_y - dest.y)
+}
+
 #[macro_export]
 macro_rules! nine_slice {
     ($name:expr) => {{
@@ -456,18 +1577,40 @@ macro_rules! nine_slice {
             let mut y: i32 = 0;
             let mut w: u32 = 0;
             let mut h: u32 = 0;
+            let mut bounds: Option<$crate::bounds::Bounds> = None;
             let mut slice_size: i32 = 0;
             let mut absolute: bool = false;
             let mut opacity: f32 = 1.0;
+            let mut color: u32 = 0xffffffff;
+            let mut top_left_color: Option<u32> = None;
+            let mut top_color: Option<u32> = None;
+            let mut top_right_color: Option<u32> = None;
+            let mut left_color: Option<u32> = None;
+            let mut center_color: Option<u32> = None;
+            let mut right_color: Option<u32> = None;
+            let mut bottom_left_color: Option<u32> = None;
+            let mut bottom_color: Option<u32> = None;
+            let mut bottom_right_color: Option<u32> = None;
+            let mut origin_x: i32 = 0;
+            let mut origin_y: i32 = 0;
+            let mut rotate: i32 = 0;
+            let mut scale: f32 = 1.0;
+            let mut dest_slice_size: Option<i32> = None;
+            let mut layer: Option<i32> = None;
 
             $($crate::paste::paste!{ [< $key >] = nine_slice!(@coerce $key, $val); })*
+            let _layer = layer.map($crate::canvas::LayerGuard::new);
 
-            let mut sx: i32 = 0;
-            let mut sy: i32 = 0;
-            let mut sw: i32 = 0;
-            let mut sh: i32 = 0;
-            let mut w_origin: u32 = w;
-            let mut h_origin: u32 = h;
+            // Bounds set the position and size together
+            if let Some(b) = bounds { x = b.x; y = b.y; w = b.w; h = b.h; }
+
+            // Regions are drawn at the pixel scale of the destination to source slice sizes
+            let dest_slice_size = dest_slice_size.unwrap_or(slice_size);
+            let pixel_scale = if slice_size > 0 && dest_slice_size > 0 {
+                dest_slice_size as f32 / slice_size as f32
+            } else {
+                1.0
+            };
 
             let mut x_origin: i32;
             let mut y_origin: i32;
@@ -481,145 +1624,63 @@ macro_rules! nine_slice {
                 y_origin = y;
             }
 
-            sw = slice_size;
-            sh = slice_size;
-
-            // Center slice scaled
-            w = w_origin - (slice_size*2) as u32;
-            h = h_origin - (slice_size*2) as u32;
-            x = x_origin + slice_size;
-            y = y_origin + slice_size;
-            sx = slice_size;
-            sy = slice_size;
-            $crate::sprite!(
-                $name,
-                x = x,
-                y = y,
-                w = w,
-                h = h,
-                sx = sx,
-                sy = sy,
-                sw = sw,
-                sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Top slice scaled
-            h = slice_size as u32;
-            y = y_origin;
-            sy = 0;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Bottom slice scaled
-            y = y_origin + h_origin as i32 - slice_size;
-            sy = 2 * slice_size;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Bottom left slice scaled
-            x = x_origin;
-            w = slice_size as u32;
-            sx = 0;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Bottom right slice scaled
-            x = x_origin + w_origin as i32 - slice_size;
-            sx = slice_size * 2;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Top right slice scaled
-            y = y_origin;
-            sy = 0;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Top left slice scaled
-            x = x_origin;
-            sx = 0;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Left slice scaled
-            y = y_origin + slice_size;
-            sy = slice_size;
-            h = h_origin - (slice_size * 2) as u32;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
-
-            // Right slice scaled
-            x = x_origin + w_origin as i32 - slice_size;
-            sx = slice_size * 2;
-            $crate::sprite!(
-                $name,
-                x = x, y = y,
-                w = w, h = h,
-                sx = sx, sy = sy,
-                sw = sw, sh = sh,
-                opacity = opacity,
-                repeat = true
-            );
+            use $crate::canvas::NineSliceRegion;
+            for (region, dest, source) in $crate::canvas::nine_slice_regions(w, h, slice_size as u32, dest_slice_size as u32) {
+                // Regional colors fall back to the base color
+                let region_color = match region {
+                    NineSliceRegion::TopLeft => top_left_color,
+                    NineSliceRegion::Top => top_color,
+                    NineSliceRegion::TopRight => top_right_color,
+                    NineSliceRegion::Left => left_color,
+                    NineSliceRegion::Center => center_color,
+                    NineSliceRegion::Right => right_color,
+                    NineSliceRegion::BottomLeft => bottom_left_color,
+                    NineSliceRegion::Bottom => bottom_color,
+                    NineSliceRegion::BottomRight => bottom_right_color,
+                }.unwrap_or(color);
+                let (dx, dy, region_origin_x, region_origin_y) =
+                    $crate::canvas::nine_slice_transform(dest, origin_x, origin_y, scale);
+                $crate::sprite!(
+                    $name,
+                    x = x_origin + dx, y = y_origin + dy,
+                    w = (dest.w as f32 / pixel_scale).round(), h = (dest.h as f32 / pixel_scale).round(),
+                    sx = source.x, sy = source.y,
+                    sw = source.w, sh = source.h,
+                    color = region_color,
+                    opacity = opacity,
+                    origin_x = (region_origin_x as f32 / pixel_scale).round(),
+                    origin_y = (region_origin_y as f32 / pixel_scale).round(),
+                    rotate = rotate,
+                    scale = scale * pixel_scale,
+                    repeat = true
+                );
+            }
         }
     }};
     (@coerce x, $val:expr) => { $val as i32; };
     (@coerce y, $val:expr) => { $val as i32; };
     (@coerce w, $val:expr) => { $val as u32; };
     (@coerce h, $val:expr) => { $val as u32; };
+    (@coerce bounds, $val:expr) => { Some($val as $crate::bounds::Bounds); };
     (@coerce slice_size, $val:expr) => { $val as i32; };
+    (@coerce dest_slice_size, $val:expr) => { Some($val as i32); };
     (@coerce absolute, $val:expr) => { $val as bool; };
     (@coerce opacity, $val:expr) => { $val as f32; };
+    (@coerce color, $val:expr) => { $val as u32; };
+    (@coerce top_left_color, $val:expr) => { Some($val as u32); };
+    (@coerce top_color, $val:expr) => { Some($val as u32); };
+    (@coerce top_right_color, $val:expr) => { Some($val as u32); };
+    (@coerce left_color, $val:expr) => { Some($val as u32); };
+    (@coerce center_color, $val:expr) => { Some($val as u32); };
+    (@coerce right_color, $val:expr) => { Some($val as u32); };
+    (@coerce bottom_left_color, $val:expr) => { Some($val as u32); };
+    (@coerce bottom_color, $val:expr) => { Some($val as u32); };
+    (@coerce bottom_right_color, $val:expr) => { Some($val as u32); };
+    (@coerce origin_x, $val:expr) => { $val as i32; };
+    (@coerce origin_y, $val:expr) => { $val as i32; };
+    (@coerce rotate, $val:expr) => { $val as i32; };
+    (@coerce scale, $val:expr) => { $val as f32; };
+    (@coerce layer, $val:expr) => { Some($val as i32); };
 }
 
 //------------------------------------------------------------------------------
@@ -637,6 +1698,20 @@ pub fn draw_rect(
     border_color: u32,
     rotation_deg: i32,
 ) {
+    let queued = queue_draw(|| DrawCommand::Rect {
+        color,
+        dx,
+        dy,
+        dw,
+        dh,
+        border_radius,
+        border_size,
+        border_color,
+        rotation_deg,
+    });
+    if queued {
+        return;
+    }
     let dest_xy = ((dx as u64) << 32) | (dy as u32 as u64);
     let dest_wh = ((dw as u64) << 32) | (dh as u32 as u64);
     let fill_ab = (color as u64) << 32;
@@ -669,8 +1744,12 @@ macro_rules! rect {
         let mut scale_x: f32 = 1.0;
         let mut scale_y: f32 = 1.0;
         let mut absolute: bool = false;
+        let mut fill: bool = true;
+        let mut opacity: f32 = 1.0;
 
+        let mut layer: Option<i32> = None;
         $($crate::paste::paste!{ [< $key >] = rect!(@coerce $key, $val); })*
+        let _layer = layer.map($crate::canvas::LayerGuard::new);
 
         // Absolute positioning
         if absolute {
@@ -683,6 +1762,11 @@ macro_rules! rect {
         w = (w as f32 * scale_x) as u32;
         h = (h as f32 * scale_y) as u32;
 
+        // Outline-only rects keep the border and clear the fill
+        if !fill { color &= 0xffffff00; }
+        color = $crate::canvas::apply_opacity(color, opacity);
+        border_color = $crate::canvas::apply_opacity(border_color, opacity);
+
         $crate::canvas::draw_rect(
             color,
             x, y, w, h,
@@ -699,9 +1783,130 @@ macro_rules! rect {
     (@coerce border_radius, $val:expr) => { $val as u32; };
     (@coerce border_width, $val:expr) => { $val as u32; };
     (@coerce border_color, $val:expr) => { $val as u32; };
+    (@coerce fill, $val:expr) => { $val as bool; };
+    (@coerce opacity, $val:expr) => { $val as f32; };
     (@coerce rotate, $val:expr) => { $val as i32; };
     (@coerce scale_x, $val:expr) => { $val as f32; };
     (@coerce scale_y, $val:expr) => { $val as f32; };
+    (@coerce layer, $val:expr) => { Some($val as i32); };
+}
+
+//------------------------------------------------------------------------------
+// Progress Bar
+//------------------------------------------------------------------------------
+
+/// Draws a horizontal progress bar filled left to right. `fill` is clamped to 0 to 1.
+pub fn progress_bar(bounds: Bounds, fill: f32, fg: u32, bg: u32) {
+    draw_progress_bar(bounds, fill, fg, bg, 0, false, None)
+}
+
+/// Draws a progress bar with rounded corners and an optional `(width, color)` border.
+/// Vertical bars fill from the bottom up.
+pub fn draw_progress_bar(
+    bounds: Bounds,
+    fill: f32,
+    fg: u32,
+    bg: u32,
+    border_radius: u32,
+    vertical: bool,
+    border: Option<(u32, u32)>,
+) {
+    let (border_width, border_color) = border.unwrap_or((0, 0));
+    let Bounds { x, y, w, h } = bounds;
+    draw_rect(bg, x, y, w, h, border_radius, border_width, border_color, 0);
+
+    // The bar fills the area inside the border
+    let inner = Bounds::new(
+        x + border_width as i32,
+        y + border_width as i32,
+        w.saturating_sub(border_width * 2),
+        h.saturating_sub(border_width * 2),
+    );
+    let inner_radius = border_radius.saturating_sub(border_width);
+    if let Some(bar) = progress_fill_bounds(inner, fill, vertical) {
+        // A full bar matches the background's rounding. A partial one can't be
+        // rounder than it is long.
+        let radius = if bar == inner {
+            inner_radius
+        } else {
+            inner_radius.min(bar.w / 2).min(bar.h / 2)
+        };
+        draw_rect(fg, bar.x, bar.y, bar.w, bar.h, radius, 0, 0, 0);
+    }
+}
+
+// The filled part of a progress bar, or `None` when it rounds to nothing
+pub(crate) fn progress_fill_bounds(bounds: Bounds, fill: f32, vertical: bool) -> Option<Bounds> {
+    let fill = if fill.is_nan() {
+        0.
+    } else {
+        fill.clamp(0., 1.)
+    };
+    let len = if vertical { bounds.h } else { bounds.w };
+    let filled = (len as f32 * fill).round() as u32;
+    if filled == 0 || bounds.w == 0 || bounds.h == 0 {
+        return None;
+    }
+    Some(if vertical {
+        Bounds::new(bounds.x, bounds.bottom() - filled as i32, bounds.w, filled)
+    } else {
+        Bounds::new(bounds.x, bounds.y, filled, bounds.h)
+    })
+}
+
+//------------------------------------------------------------------------------
+// Checkerboard
+//------------------------------------------------------------------------------
+
+/// Fills `bounds` with alternating `color_a` and `color_b` squares, starting with
+/// `color_a` at the top-left corner. Nothing is drawn when `cell` is 0.
+pub fn checkerboard(bounds: Bounds, cell: u32, color_a: u32, color_b: u32) {
+    draw_checkerboard(bounds, cell, color_a, color_b, false)
+}
+
+/// Same as `checkerboard`, but with `world_aligned` the squares line up with the
+/// world origin instead of the bounds, so the pattern stays put as the bounds move.
+pub fn draw_checkerboard(
+    bounds: Bounds,
+    cell: u32,
+    color_a: u32,
+    color_b: u32,
+    world_aligned: bool,
+) {
+    if cell == 0 || bounds.w == 0 || bounds.h == 0 {
+        return;
+    }
+    let Bounds { x, y, w, h } = bounds;
+    draw_rect(color_a, x, y, w, h, 0, 0, 0, 0);
+    for b in checker_cells(bounds, cell, world_aligned) {
+        draw_rect(color_b, b.x, b.y, b.w, b.h, 0, 0, 0, 0);
+    }
+}
+
+// The `color_b` squares of a checkerboard, cropped to the bounds
+pub(crate) fn checker_cells(bounds: Bounds, cell: u32, world_aligned: bool) -> Vec<Bounds> {
+    if cell == 0 || bounds.w == 0 || bounds.h == 0 {
+        return vec![];
+    }
+    let size = cell as i32;
+    let (ox, oy) = if world_aligned {
+        (0, 0)
+    } else {
+        (bounds.x, bounds.y)
+    };
+    let cols = (bounds.x - ox).div_euclid(size)..=(bounds.right() - 1 - ox).div_euclid(size);
+    let rows = (bounds.y - oy).div_euclid(size)..=(bounds.bottom() - 1 - oy).div_euclid(size);
+    let mut cells = vec![];
+    for row in rows {
+        for col in cols.clone() {
+            if (row + col).rem_euclid(2) == 0 {
+                continue;
+            }
+            let square = Bounds::new(ox + col * size, oy + row * size, cell, cell);
+            cells.push(square.intersection(&bounds));
+        }
+    }
+    cells
 }
 
 #[macro_export]
@@ -713,7 +1918,11 @@ macro_rules! path {
         let mut width: u32 = 1;
         let mut border_radius: u32 = 0;
         let mut absolute: bool = false;
+        let mut dash: u32 = 0;
+        let mut gap: u32 = 0;
+        let mut layer: Option<i32> = None;
         $($crate::paste::paste!{ [< $key >] = path!(@coerce $key, $val); })*
+        let _layer = layer.map($crate::canvas::LayerGuard::new);
 
         // Calculate differences and distance
         let delta_x = (end.0 - start.0) as f64;
@@ -729,25 +1938,61 @@ macro_rules! path {
         let mut y = (start.1 + end.1) / 2;
 
         // Absolute positioning
+        let mut offset_x = 0;
+        let mut offset_y = 0;
         if absolute {
             let (cx, cy, _) = crate::cam!();
             let [w, h] = crate::canvas_size!();
-            x += cx - (w as i32 / 2);
-            y += cy - (h as i32 / 2);
+            offset_x = cx - (w as i32 / 2);
+            offset_y = cy - (h as i32 / 2);
         }
+        x += offset_x;
+        y += offset_y;
+
+        if dash == 0 {
+            // Draw the rectangle as a thin line with rotation around its center
+            $crate::canvas::draw_rect(
+                color,
+                x - (distance / 2) as i32, // Adjust x to start from the midpoint
+                y - (width / 2) as i32,   // Center y based on line width
+                distance,                 // Width of the rectangle is the distance
+                width,                    // Height of the rectangle is the line width
+                border_radius,            // Border radius (if any)
+                0,                        // Border width (none)
+                0,                        // Border color (none)
+                angle                     // Rotation angle
+            )
+        } else {
+            // Unit vector along the line
+            let (ux, uy) = if distance > 0 {
+                (delta_x / distance as f64, delta_y / distance as f64)
+            } else {
+                (0., 0.)
+            };
 
-        // Draw the rectangle as a thin line with rotation around its center
-        $crate::canvas::draw_rect(
-            color,
-            x - (distance / 2) as i32, // Adjust x to start from the midpoint
-            y - (width / 2) as i32,   // Center y based on line width
-            distance,                 // Width of the rectangle is the distance
-            width,                    // Height of the rectangle is the line width
-            border_radius,            // Border radius (if any)
-            0,                        // Border width (none)
-            0,                        // Border color (none)
-            angle                     // Rotation angle
-        )
+            // Walk the line, drawing each dash around its own midpoint
+            let mut dash_start = 0;
+            while dash_start < distance {
+                // Clip the last dash so it doesn't overshoot the end
+                let dash_end = (dash_start + dash).min(distance);
+                let dash_len = dash_end - dash_start;
+                let t = (dash_start + dash_end) as f64 / 2.0;
+                let dx = (start.0 as f64 + ux * t) as i32 + offset_x;
+                let dy = (start.1 as f64 + uy * t) as i32 + offset_y;
+                $crate::canvas::draw_rect(
+                    color,
+                    dx - (dash_len / 2) as i32,
+                    dy - (width / 2) as i32,
+                    dash_len,
+                    width,
+                    border_radius,
+                    0,
+                    0,
+                    angle
+                );
+                dash_start += dash + gap;
+            }
+        }
     }};
     (@coerce start, $val:expr) => { ($val.0 as i32, $val.1 as i32); };
     (@coerce end, $val:expr) => { ($val.0 as i32, $val.1 as i32); };
@@ -755,6 +2000,9 @@ macro_rules! path {
     (@coerce color, $val:expr) => { $val as u32; };
     (@coerce width, $val:expr) => { $val as u32; };
     (@coerce border_radius, $val:expr) => { $val as u32; };
+    (@coerce dash, $val:expr) => { $val as u32; };
+    (@coerce gap, $val:expr) => { $val as u32; };
+    (@coerce layer, $val:expr) => { Some($val as i32); };
 }
 
 //------------------------------------------------------------------------------
@@ -774,7 +2022,9 @@ macro_rules! circ {
         let mut scale_x: f32 = 1.0;
         let mut scale_y: f32 = 1.0;
         let mut absolute: bool = false;
+        let mut layer: Option<i32> = None;
         $($crate::paste::paste!{ [< $key >] = circ!(@coerce $key, $val); })*
+        let _layer = layer.map($crate::canvas::LayerGuard::new);
         // Absolute positioning
         if absolute {
             let (cx, cy, _) = crate::cam!();
@@ -804,12 +2054,109 @@ macro_rules! circ {
     (@coerce rotate, $val:expr) => { $val as i32; };
     (@coerce scale_x, $val:expr) => { $val as f32; };
     (@coerce scale_y, $val:expr) => { $val as f32; };
+    (@coerce layer, $val:expr) => { Some($val as i32); };
 }
 
 //------------------------------------------------------------------------------
 // Ellipse
 //------------------------------------------------------------------------------
 
+/// Draws an ellipse filling `dw` by `dh`. A circle is a single rounded rect, but
+/// other ellipses take one `draw_rect` per row, or up to three with a border, so a
+/// tall ellipse costs hundreds of host draws.
+pub fn draw_ellipse(
+    color: u32,
+    dx: i32,
+    dy: i32,
+    dw: u32,
+    dh: u32,
+    border_size: u32,
+    border_color: u32,
+    rotation_deg: i32,
+) {
+    // A circle is a single rounded rect, so it needs no rows at all
+    if dw == dh {
+        draw_rect(color, dx, dy, dw, dh, dw, border_size, border_color, 0);
+        return;
+    }
+    let border_size = border_size.min(dw / 2).min(dh / 2);
+    for (row, start, end, is_border) in ellipse_rows(dw, dh, border_size, rotation_deg) {
+        let color = if is_border { border_color } else { color };
+        let len = (end - start) as u32;
+        draw_rect(color, dx + start, dy + row, len, 1, 0, 0, 0, 0);
+    }
+}
+
+// Unrotated rows of an ellipse with a border, as (row, start, end, is_border)
+// relative to the top-left of its w x h box. Each row holds the fill span and
+// up to two border spans.
+pub(crate) fn ellipse_rows(
+    w: u32,
+    h: u32,
+    border_size: u32,
+    rotation_deg: i32,
+) -> Vec<(i32, i32, i32, bool)> {
+    let inner = rotated_ellipse_spans(w - border_size * 2, h - border_size * 2, rotation_deg);
+    let offset = border_size as i32;
+    let mut rows = vec![];
+    for (row, start, end) in rotated_ellipse_spans(w, h, rotation_deg) {
+        let inner_span = inner
+            .iter()
+            .find(|span| span.0 + offset == row)
+            .map(|&(_, inner_start, inner_end)| (inner_start + offset, inner_end + offset));
+        match inner_span {
+            // Fill with border on either side
+            Some((inner_start, inner_end)) if border_size > 0 => {
+                let inner_start = inner_start.max(start);
+                let inner_end = inner_end.min(end);
+                rows.push((row, start, inner_start, true));
+                rows.push((row, inner_start, inner_end, false));
+                rows.push((row, inner_end, end, true));
+            }
+            // No border
+            Some(_) => rows.push((row, start, end, false)),
+            // Rows beyond the inner ellipse are all border
+            None => rows.push((row, start, end, true)),
+        }
+    }
+    rows.retain(|&(_, start, end, _)| end > start);
+    rows
+}
+
+// Horizontal spans of an ellipse inscribed in a w x h box and rotated about its
+// center, as (row, start, end) relative to the box's top-left corner. The whole
+// shape is rotated before it's split into rows, so neighboring rows stay flush.
+pub(crate) fn rotated_ellipse_spans(w: u32, h: u32, rotation_deg: i32) -> Vec<(i32, i32, i32)> {
+    if w == 0 || h == 0 {
+        return vec![];
+    }
+    let radius_x = w as f32 / 2.;
+    let radius_y = h as f32 / 2.;
+    let (sin, cos) = (rotation_deg as f32).to_radians().sin_cos();
+    // The rotated ellipse is a*x^2 + b*x*y + c*y^2 = 1 around its center
+    let (inv_x, inv_y) = (1. / (radius_x * radius_x), 1. / (radius_y * radius_y));
+    let a = cos * cos * inv_x + sin * sin * inv_y;
+    let b = 2. * sin * cos * (inv_x - inv_y);
+    let c = sin * sin * inv_x + cos * cos * inv_y;
+    let half_h = (radius_x * radius_x * sin * sin + radius_y * radius_y * cos * cos).sqrt();
+    let top = (radius_y - half_h).floor() as i32;
+    let bottom = (radius_y + half_h).ceil() as i32;
+    (top..bottom)
+        .filter_map(|row| {
+            // Solve for x at the middle of the row
+            let y = row as f32 + 0.5 - radius_y;
+            let disc = (b * y) * (b * y) - 4. * a * (c * y * y - 1.);
+            if disc < 0. {
+                return None;
+            }
+            let root = disc.sqrt();
+            let start = ((-b * y - root) / (2. * a) + radius_x).round() as i32;
+            let end = ((-b * y + root) / (2. * a) + radius_x).round() as i32;
+            (end > start).then_some((row, start, end))
+        })
+        .collect()
+}
+
 #[macro_export]
 macro_rules! ellipse {
     ($( $key:ident = $val:expr ),* $(,)*) => {{
@@ -820,11 +2167,17 @@ macro_rules! ellipse {
         let mut h: u32 = 0;
         let mut border_width: u32 = 0;
         let mut border_color: u32 = 0xffffffff;
+        let mut opacity: f32 = 1.0;
         let mut rotate: i32 = 0;
         let mut scale_x: f32 = 1.0;
         let mut scale_y: f32 = 1.0;
         let mut absolute: bool = false;
+        let mut layer: Option<i32> = None;
         $($crate::paste::paste!{ [< $key >] = ellipse!(@coerce $key, $val); })*
+        let _layer = layer.map($crate::canvas::LayerGuard::new);
+        // Set opacity
+        color = $crate::canvas::apply_opacity(color, opacity);
+        border_color = $crate::canvas::apply_opacity(border_color, opacity);
         // Absolute positioning
         if absolute {
             let (cx, cy, _) = crate::cam!();
@@ -834,11 +2187,10 @@ macro_rules! ellipse {
         }
         w = (w as f32 * scale_x) as u32;
         h = (h as f32 * scale_y) as u32;
-        let border_radius = w.max(h);
-        $crate::canvas::draw_rect(
+        $crate::canvas::draw_ellipse(
             color,
             x, y, w, h,
-            border_radius, border_width, border_color,
+            border_width, border_color,
             rotate
         )
     }};
@@ -850,9 +2202,11 @@ macro_rules! ellipse {
     (@coerce h, $val:expr) => { $val as u32; };
     (@coerce border_width, $val:expr) => { $val as u32; };
     (@coerce border_color, $val:expr) => { $val as u32; };
+    (@coerce opacity, $val:expr) => { $val as f32; };
     (@coerce rotate, $val:expr) => { $val as i32; };
     (@coerce scale_x, $val:expr) => { $val as f32; };
     (@coerce scale_y, $val:expr) => { $val as f32; };
+    (@coerce layer, $val:expr) => { Some($val as i32); };
 }
 
 //------------------------------------------------------------------------------
@@ -884,11 +2238,63 @@ impl Into<u8> for Font {
 }
 
 pub fn text(x: i32, y: i32, font: Font, color: u32, text: &str) {
+    let queued = queue_draw(|| DrawCommand::Text {
+        x,
+        y,
+        font,
+        color,
+        text: text.to_string(),
+    });
+    if queued {
+        return;
+    }
     let ptr = text.as_ptr();
     let len = text.len() as u32;
     ffi::canvas::text(x, y, font.into(), color, ptr, len)
 }
 
+// Draws text over an optional (dx, dy, color) drop shadow and (thickness, color) outline
+pub fn text_styled(
+    x: i32,
+    y: i32,
+    font: Font,
+    color: u32,
+    s: &str,
+    shadow: Option<(i32, i32, u32)>,
+    outline: Option<(u32, u32)>,
+) {
+    if let Some((dx, dy, shadow_color)) = shadow {
+        text(x + dx, y + dy, font, shadow_color, s);
+    }
+    if let Some((thickness, outline_color)) = outline {
+        // Each ring of the outline is drawn in all 8 directions
+        for r in 1..=thickness as i32 {
+            for (dx, dy) in [
+                (-r, -r),
+                (0, -r),
+                (r, -r),
+                (-r, 0),
+                (r, 0),
+                (-r, r),
+                (0, r),
+                (r, r),
+            ] {
+                text(x + dx, y + dy, font, outline_color, s);
+            }
+        }
+    }
+    text(x, y, font, color, s);
+}
+
+// The first `chars` characters of `s`, for typewriter effects. Counts chars
+// rather than bytes so multibyte characters are never split.
+pub fn reveal_text(s: &str, chars: usize) -> &str {
+    match s.char_indices().nth(chars) {
+        Some((end, _)) => &s[..end],
+        None => s,
+    }
+}
+
 #[macro_export]
 macro_rules! text {
     ($text:expr) => {{
@@ -900,7 +2306,16 @@ macro_rules! text {
         let mut font: Font = Font::M;
         let mut color: u32 = 0xffffffff;
         let mut absolute: bool = false;
+        let mut opacity: f32 = 1.0;
+        let mut shadow_x: i32 = 1;
+        let mut shadow_y: i32 = 1;
+        let mut shadow_color: Option<u32> = None;
+        let mut outline: u32 = 0;
+        let mut outline_color: u32 = 0x000000ff;
+        let mut reveal: usize = usize::MAX;
+        let mut layer: Option<i32> = None;
         $($crate::paste::paste!{ [< $key >] = text!(@coerce $key, $val); })*
+        let _layer = layer.map($crate::canvas::LayerGuard::new);
         // Absolute positioning
         if absolute {
             let (cx, cy, _) = crate::cam!();
@@ -908,7 +2323,12 @@ macro_rules! text {
             x += cx - (w as i32 / 2);
             y += cy - (h as i32 / 2);
         }
-        $crate::canvas::text(x, y, font, color, $text)
+        // Shadow and outline fade along with the text
+        let shadow = shadow_color.map(|c| (shadow_x, shadow_y, $crate::canvas::apply_opacity(c, opacity)));
+        let outline = (outline > 0).then(|| (outline, $crate::canvas::apply_opacity(outline_color, opacity)));
+        let color = $crate::canvas::apply_opacity(color, opacity);
+        let s = $crate::canvas::reveal_text($text, reveal);
+        $crate::canvas::text_styled(x, y, font, color, s, shadow, outline)
     }};
     ($text:expr, $( $arg:expr ),* ; $( $key:ident = $val:expr ),* $(,)*) => {{
         let mut x: i32 = 0;
@@ -916,7 +2336,16 @@ macro_rules! text {
         let mut font: Font = Font::M;
         let mut color: u32 = 0xffffffff;
         let mut absolute: bool = false;
+        let mut opacity: f32 = 1.0;
+        let mut shadow_x: i32 = 1;
+        let mut shadow_y: i32 = 1;
+        let mut shadow_color: Option<u32> = None;
+        let mut outline: u32 = 0;
+        let mut outline_color: u32 = 0x000000ff;
+        let mut reveal: usize = usize::MAX;
+        let mut layer: Option<i32> = None;
         $(paste::paste! { [< $key >] = text!(@coerce $key, $val); })*
+        let _layer = layer.map($crate::canvas::LayerGuard::new);
         // Absolute positioning
         if absolute {
             let (cx, cy, _) = crate::cam!();
@@ -924,11 +2353,676 @@ macro_rules! text {
             x += cx - (w as i32 / 2);
             y += cy - (h as i32 / 2);
         }
-        $crate::canvas::text(x, y, font, color, &format!($text, $($arg),*))
+        // Shadow and outline fade along with the text
+        let shadow = shadow_color.map(|c| (shadow_x, shadow_y, $crate::canvas::apply_opacity(c, opacity)));
+        let outline = (outline > 0).then(|| (outline, $crate::canvas::apply_opacity(outline_color, opacity)));
+        let color = $crate::canvas::apply_opacity(color, opacity);
+        let s = format!($text, $($arg),*);
+        let s = $crate::canvas::reveal_text(&s, reveal);
+        $crate::canvas::text_styled(x, y, font, color, s, shadow, outline)
     }};
     (@coerce x, $val:expr) => { $val as i32; };
     (@coerce y, $val:expr) => { $val as i32; };
     (@coerce absolute, $val:expr) => { $val as bool; };
     (@coerce font, $val:expr) => { $val as Font; };
     (@coerce color, $val:expr) => { $val as u32; };
+    (@coerce opacity, $val:expr) => { $val as f32; };
+    (@coerce shadow_x, $val:expr) => { $val as i32; };
+    (@coerce shadow_y, $val:expr) => { $val as i32; };
+    (@coerce shadow_color, $val:expr) => { Some($val as u32); };
+    (@coerce outline, $val:expr) => { $val as u32; };
+    (@coerce outline_color, $val:expr) => { $val as u32; };
+    // Only draws this many characters
+    (@coerce reveal, $val:expr) => { $val as usize; };
+    (@coerce layer, $val:expr) => { Some($val as i32); };
+}
+
+//------------------------------------------------------------------------------
+// Capture
+//------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    pub width: u32,
+    pub height: u32,
+    // RGBA8 pixels, row-major from the top-left
+    pub pixels: Vec<u8>,
+}
+impl Capture {
+    // Copies `width`-wide rows of pixels in at `x`, `y`, dropping anything outside
+    pub(crate) fn blit(&mut self, x: i32, y: i32, width: u32, pixels: &[u8]) {
+        let area = Bounds::new(0, 0, self.width, self.height);
+        for (row, src) in pixels.chunks_exact(width as usize * 4).enumerate() {
+            for (col, rgba) in src.chunks_exact(4).enumerate() {
+                let (px, py) = (x + col as i32, y + row as i32);
+                if area.contains(px, py) {
+                    let i = (py as usize * self.width as usize + px as usize) * 4;
+                    self.pixels[i..i + 4].copy_from_slice(rgba);
+                }
+            }
+        }
+    }
+
+    /// Gets the 0xRRGGBBAA color at a pixel, or 0 if it's out of range
+    pub fn pixel(&self, x: i32, y: i32) -> u32 {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return 0;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        match self.pixels.get(i..i + 4) {
+            Some(rgba) => u32::from_be_bytes([rgba[0], rgba[1], rgba[2], rgba[3]]),
+            None => 0,
+        }
+    }
+}
+
+/// Reads back the current framebuffer. This stalls on the GPU, so it's best kept
+/// to one-off moments like save-slot thumbnails rather than called every frame.
+/// Draws queued in an open `LayerFrame` are drawn first so they're included.
+pub fn capture() -> Option<Capture> {
+    let [width, height] = canvas_size();
+    capture_rect(0, 0, width, height)
+}
+
+/// Reads back a region of the framebuffer in screen pixels, ignoring the camera.
+/// Parts of the region that are off-screen read as 0. Like `capture`, this stalls
+/// on the GPU, so read one region and sample it with `Capture::pixel` rather than
+/// reading pixel by pixel.
+pub fn capture_rect(x: i32, y: i32, width: u32, height: u32) -> Option<Capture> {
+    draw_queued();
+    let [w, h] = canvas_size();
+    let area = Bounds::new(x, y, width, height).intersection(&Bounds::new(0, 0, w, h));
+    let mut capture = Capture {
+        width,
+        height,
+        pixels: vec![0; width as usize * height as usize * 4],
+    };
+    if area.w == 0 || area.h == 0 {
+        return Some(capture);
+    }
+    let len = area.w as usize * area.h as usize * 4;
+    let mut pixels = vec![0; len];
+    let n = ffi::canvas::read_pixels(
+        area.x,
+        area.y,
+        area.w,
+        area.h,
+        pixels.as_mut_ptr(),
+        len as u32,
+    );
+    if n != len as i32 {
+        return None;
+    }
+    capture.blit(area.x - x, area.y - y, area.w, &pixels);
+    Some(capture)
+}
+
+/// Reads the 0xRRGGBBAA color at a screen pixel, or 0 if it's off-screen or
+/// can't be read. Each call is a GPU read, so use `capture_rect` to sample many.
+pub fn get_pixel(x: i32, y: i32) -> u32 {
+    capture_rect(x, y, 1, 1).map_or(0, |capture| capture.pixel(0, 0))
+}
+
+/// Writes a 0xRRGGBBAA color to a screen pixel right away, ignoring the camera, so
+/// `get_pixel` reads it back. Off-screen pixels are dropped. Each call is a GPU
+/// write, so prefer rects or sprites for anything larger.
+pub fn set_pixel(x: i32, y: i32, color: u32) {
+    // Queued draws go first so they land under the pixel
+    draw_queued();
+    let [w, h] = canvas_size();
+    if !Bounds::new(0, 0, w, h).contains(x, y) {
+        return;
+    }
+    let rgba = color.to_be_bytes();
+    ffi::canvas::write_pixels(x, y, 1, 1, rgba.as_ptr(), 4);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A plain, untinted 16x16 sprite quad
+    fn sprite_quad(dx: i32, dy: i32) -> SpriteDraw {
+        SpriteDraw {
+            dx,
+            dy,
+            dw: 16,
+            dh: 16,
+            sx: 0,
+            sy: 0,
+            sw: 16,
+            sh: 16,
+            tx: 0,
+            ty: 0,
+            color: 0xffffffff,
+            background_color: 0,
+            border_radius: 0,
+            origin_x: 0,
+            origin_y: 0,
+            rotation_deg: 0,
+            flags: 0,
+            tint_color: 0xffffffff,
+            tint_strength: 0.,
+        }
+    }
+
+    #[test]
+    fn test_flush_steps_restore_each_draw_camera() {
+        let rect = |dx: i32| DrawCommand::Rect {
+            color: 0,
+            dx,
+            dy: 0,
+            dw: 1,
+            dh: 1,
+            border_radius: 0,
+            border_size: 0,
+            border_color: 0,
+            rotation_deg: 0,
+        };
+        let queued = |layer: i32, camera: (f32, f32, f32), dx: i32| QueuedDraw {
+            layer,
+            clip: None,
+            camera,
+            draw: rect(dx),
+        };
+        let world = (100., 50., 1.);
+        let screen = (0., 0., 1.);
+        // World drawn on top of a HUD queued after the camera was reset
+        let draws = vec![queued(1, world, 1), queued(0, screen, 2)];
+        let steps = flush_steps(draws, screen, None);
+        let steps = steps
+            .iter()
+            .map(|step| match step {
+                FlushStep::Camera(camera) => format!("camera {:?}", camera),
+                FlushStep::Clip(clip) => format!("clip {:?}", clip),
+                FlushStep::Draw(DrawCommand::Rect { dx, .. }) => format!("rect {}", dx),
+                FlushStep::Draw(_) => "draw".to_string(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [
+                "rect 2",
+                "camera (100.0, 50.0, 1.0)",
+                "rect 1",
+                "camera (0.0, 0.0, 1.0)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pixel_access_draws_the_queue_inside_a_frame() {
+        let frame = LayerFrame::begin();
+        draw_rect(0xffffffff, 0, 0, 4, 4, 0, 0, 0, 0);
+        assert_eq!(layer_queue().draws.len(), 1);
+        capture_rect(0, 0, 1, 1);
+        assert!(layer_queue().draws.is_empty());
+        // The frame stays open for later draws
+        draw_rect(0xffffffff, 0, 0, 4, 4, 0, 0, 0, 0);
+        assert_eq!(layer_queue().draws.len(), 1);
+        set_pixel(0, 0, 0xff0000ff);
+        assert!(layer_queue().draws.is_empty());
+        draw_rect(0xffffffff, 0, 0, 4, 4, 0, 0, 0, 0);
+        drop(frame);
+        assert!(layer_queue().draws.is_empty() && !layer_queue().active);
+    }
+
+    #[test]
+    fn test_fixed_draw_keeps_its_camera_when_flushed() {
+        // A HUD sprite pinned to the screen's top-left under a zoomed camera
+        let camera = (100., 50., 2.);
+        let (x, y, zoom) = screen_to_world_at(camera, [320, 240], 0, 0);
+        assert_eq!((x, y, zoom), (20, -10, 2.));
+        let draw = QueuedDraw {
+            layer: DEFAULT_LAYER,
+            clip: None,
+            camera,
+            draw: DrawCommand::Text {
+                x,
+                y,
+                font: Font::M,
+                color: 0xffffffff,
+                text: "HUD".to_string(),
+            },
+        };
+        // The camera moves before the frame is flushed
+        let moved = (300., 0., 1.);
+        let steps = flush_steps(vec![draw], moved, None);
+        assert!(matches!(steps[0], FlushStep::Camera(c) if c == camera));
+        assert!(matches!(
+            steps[1],
+            FlushStep::Draw(DrawCommand::Text { x: 20, y: -10, .. })
+        ));
+        assert!(matches!(steps[2], FlushStep::Camera(c) if c == moved));
+        assert_eq!(steps.len(), 3);
+    }
+
+    #[test]
+    fn test_read_resized() {
+        let host = |size: usize| {
+            let mut calls = 0;
+            move |buf: &mut [u8]| {
+                calls += 1;
+                let n = size.min(buf.len());
+                buf[..n].fill(calls);
+                size
+            }
+        };
+        let (data, len) = read_resized(8, host(5));
+        assert_eq!((data.len(), len, data[0]), (8, 5, 1));
+        // Too big for the first buffer, so it's read again at full size
+        let (data, len) = read_resized(8, host(20));
+        assert_eq!((data.len(), len), (20, 20));
+        assert!(data.iter().all(|&b| b == 2));
+    }
+
+    #[test]
+    fn test_blend_flags_need_unstable_host() {
+        assert_eq!(BlendMode::Normal.flags(), 0);
+        let additive = BlendMode::Additive.flags();
+        if cfg!(feature = "unstable-host") {
+            assert_eq!(additive, flags::SPRITE_BLEND_ADDITIVE);
+        } else {
+            assert_eq!(additive, 0);
+        }
+    }
+
+    #[test]
+    fn test_tint_blends_rgb_and_keeps_alpha() {
+        assert_eq!(tint(0xffffff80, 0x000000ff, 1.), 0x00000080);
+        assert_eq!(tint(0xffffffff, 0x000000ff, 0.5), 0x808080ff);
+        assert_eq!(tint(0x204060ff, 0xff0000ff, 0.), 0x204060ff);
+        assert_eq!(tint(0x204060ff, 0xff0000ff, f32::NAN), 0x204060ff);
+        assert_eq!(tint(0x204060ff, 0xff0000ff, 2.), 0xff0000ff);
+    }
+
+    #[test]
+    fn test_nine_slice_regions_tile_the_panel() {
+        let regions = nine_slice_regions(100, 60, 8, 8);
+        let area: u32 = regions.iter().map(|(_, dest, _)| dest.w * dest.h).sum();
+        assert_eq!(area, 100 * 60);
+        let (_, dest, source) = regions[8];
+        assert_eq!(dest, Bounds::new(92, 52, 8, 8));
+        assert_eq!(source, Bounds::new(16, 16, 8, 8));
+        let (_, dest, source) = regions[4];
+        assert_eq!(dest, Bounds::new(8, 8, 84, 44));
+        assert_eq!(source, Bounds::new(8, 8, 8, 8));
+    }
+
+    #[test]
+    fn test_nine_slice_dest_slice_size() {
+        // Corners are drawn larger than their source slices
+        let regions = nine_slice_regions(100, 60, 8, 16);
+        assert_eq!(regions[0].1, Bounds::new(0, 0, 16, 16));
+        assert_eq!(regions[0].2, Bounds::new(0, 0, 8, 8));
+        assert_eq!(regions[4].1, Bounds::new(16, 16, 68, 28));
+        // Corners shrink rather than overlap on a small panel
+        let regions = nine_slice_regions(20, 9, 8, 16);
+        assert_eq!(regions[0].1, Bounds::new(0, 0, 10, 4));
+        assert_eq!(regions[8].1, Bounds::new(10, 5, 10, 4));
+        assert_eq!(regions[4].1, Bounds::new(10, 4, 0, 1));
+    }
+
+    #[test]
+    fn test_nine_slice_rotates_as_one_piece() {
+        let (origin_x, origin_y, scale) = (50, 30, 2.0);
+        let (sin, cos) = 90f32.to_radians().sin_cos();
+        // Where a region's top-left corner ends up after rotating around its own origin
+        let corner = |dest: Bounds| {
+            let (x, y, ox, oy) = nine_slice_transform(dest, origin_x, origin_y, scale);
+            let (px, py) = ((x + ox * 2) as f32, (y + oy * 2) as f32);
+            let (rx, ry) = (x as f32 - px, y as f32 - py);
+            (
+                (px + rx * cos - ry * sin).round(),
+                (py + rx * sin + ry * cos).round(),
+            )
+        };
+        let regions = nine_slice_regions(100, 60, 8, 8);
+        // Every region pivots around the same point
+        for (_, dest, _) in regions {
+            let (x, y, ox, oy) = nine_slice_transform(dest, origin_x, origin_y, scale);
+            assert_eq!((x + ox * 2, y + oy * 2), (100, 60));
+        }
+        // A quarter turn maps the panel's top edge onto a vertical line
+        assert_eq!(corner(regions[0].1), (160., -40.));
+        assert_eq!(corner(regions[2].1), (160., 144.));
+        assert_eq!(corner(regions[6].1), (56., -40.));
+    }
+
+    #[test]
+    fn test_batch_groups_without_reordering_overlaps() {
+        let quad = |dx: i32| sprite_quad(dx, 0);
+        fn textures(batch: &Batch) -> Vec<&str> {
+            batch.groups.iter().map(|(key, _)| key.as_str()).collect()
+        }
+
+        let mut batch = Batch::new();
+        batch.add("a", quad(0));
+        batch.add("b", quad(100));
+        batch.add("a", quad(200));
+        assert_eq!(textures(&batch), ["a", "b"]);
+        assert_eq!(batch.len(), 3);
+
+        // The second "a" overlaps "b", so it has to be drawn after it
+        let mut batch = Batch::new();
+        batch.add("a", quad(0));
+        batch.add("b", quad(100));
+        batch.add("a", quad(108));
+        assert_eq!(textures(&batch), ["a", "b", "a"]);
+
+        batch.flush();
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn test_apply_opacity_multiplies_alpha() {
+        assert_eq!(apply_opacity(0xff0000ff, 1.0), 0xff0000ff);
+        assert_eq!(apply_opacity(0xff0000ff, 0.0), 0xff000000);
+        assert_eq!(apply_opacity(0xff000080, 1.0), 0xff000080);
+        assert!(apply_opacity(0xff000080, 0.5) & 0xff < 0x80);
+    }
+
+    #[test]
+    fn test_scroll_offset_wraps_at_source_size() {
+        assert_eq!(scroll_offset(0.5, 16), 0);
+        assert_eq!(scroll_offset(17.25, 16), 1);
+        assert_eq!(scroll_offset(-0.5, 16), 15);
+        assert_eq!(scroll_offset(-16.0, 16), 0);
+        assert_eq!(scroll_offset(-0.000001, 16), 15);
+        assert_eq!(scroll_offset(5.0, 0), 0);
+        assert_eq!(scroll_offset(f32::NAN, 16), 0);
+        // Each pixel step is the same size across zero
+        let steps: Vec<i32> = (-4..4).map(|i| scroll_offset(i as f32 * 0.5, 16)).collect();
+        assert_eq!(steps, vec![14, 14, 15, 15, 0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn test_progress_fill_bounds() {
+        let b = Bounds::new(10, 20, 100, 8);
+        assert_eq!(progress_fill_bounds(b, 0.0, false), None);
+        assert_eq!(progress_fill_bounds(b, 0.001, false), None);
+        assert_eq!(progress_fill_bounds(b, f32::NAN, false), None);
+        assert_eq!(
+            progress_fill_bounds(b, 0.25, false),
+            Some(Bounds::new(10, 20, 25, 8))
+        );
+        assert_eq!(progress_fill_bounds(b, 1.0, false), Some(b));
+        assert_eq!(progress_fill_bounds(b, 3.0, false), Some(b));
+        assert_eq!(
+            progress_fill_bounds(b, 0.5, true),
+            Some(Bounds::new(10, 24, 100, 4))
+        );
+    }
+
+    #[test]
+    fn test_sprite_shadow() {
+        let draw = SpriteDraw {
+            background_color: 0x00ff00ff,
+            origin_x: 8,
+            origin_y: 8,
+            ..sprite_quad(10, 20)
+        };
+        let shadow = draw.shadow(0x00000080, 2, 1, 0.5);
+        assert_eq!(
+            (shadow.dx, shadow.dy, shadow.dw, shadow.dh),
+            (12, 29, 16, 8)
+        );
+        assert_eq!(shadow.origin_y, 4);
+        assert_eq!(shadow.color, 0xffffff80);
+        assert_eq!((shadow.tint_color, shadow.tint_strength), (0x000000ff, 1.));
+        assert_eq!(shadow.background_color, 0);
+        assert_ne!(shadow.flags & flags::SPRITE_COVER, 0);
+        assert_eq!(draw.shadow(0x000000ff, 0, 0, 1.).flags, 0);
+    }
+
+    #[test]
+    fn test_sprite_tweens() {
+        // Unstarted tweens read as their start, finished ones hold their end
+        let fade = Tween {
+            end: 1.,
+            duration: 30,
+            ..Tween::new(0.)
+        };
+        let mut draw = sprite_quad(10, 20);
+        draw.apply_tween_position(
+            &Tween::new(40),
+            &Tween {
+                end: 8,
+                ..Tween::new(0)
+            },
+        );
+        assert_eq!((draw.dx, draw.dy), (40, 8));
+        draw.apply_tween_opacity(&fade);
+        assert_eq!(draw.color, 0xffffff00);
+        // Setting the alpha doesn't compound when applied every frame
+        let half = Tween::new(0.5);
+        draw.apply_tween_opacity(&half);
+        draw.apply_tween_opacity(&half);
+        assert_eq!(draw.color, 0xffffff80);
+        assert_eq!(fade.start_tick, None);
+    }
+
+    #[test]
+    fn test_rotated_bounds() {
+        let bounds = Bounds::new(10, 20, 40, 10);
+        assert_eq!(rotated_bounds(bounds, (0, 0), 360), bounds);
+        // A quarter turn about the top-left swings the sprite to the left
+        assert_eq!(
+            rotated_bounds(bounds, (0, 0), 90),
+            Bounds::new(0, 20, 10, 40)
+        );
+        // About the center it stays centered
+        assert_eq!(
+            rotated_bounds(bounds, (20, 5), 90),
+            Bounds::new(25, 5, 10, 40)
+        );
+    }
+
+    #[test]
+    fn test_transform_compose() {
+        let parent = Transform2D::new(100., 50.).rotated(90.).scaled(2., 2.);
+        let child = parent.compose(&Transform2D::new(10., 0.).rotated(45.));
+        assert!((child.x - 100.).abs() < 1e-4);
+        assert!((child.y - 70.).abs() < 1e-4);
+        assert_eq!(
+            (child.rotation, child.scale_x, child.scale_y),
+            (135., 2., 2.)
+        );
+        let identity = Transform2D::default();
+        assert_eq!(identity.compose(&child), child);
+    }
+
+    #[test]
+    fn test_fit_mode() {
+        // A 20x10 source in a 10x10 destination
+        assert_eq!(
+            FitMode::Stretch.apply(20, 10, 10, 10),
+            (Bounds::new(0, 0, 20, 10), Bounds::new(0, 0, 10, 10))
+        );
+        assert_eq!(
+            FitMode::Cover.apply(20, 10, 10, 10),
+            (Bounds::new(5, 0, 10, 10), Bounds::new(0, 0, 10, 10))
+        );
+        assert_eq!(
+            FitMode::Contain.apply(20, 10, 10, 10),
+            (Bounds::new(0, 0, 20, 10), Bounds::new(0, 2, 10, 5))
+        );
+        // A tall source
+        assert_eq!(
+            FitMode::Cover.apply(8, 16, 16, 16),
+            (Bounds::new(0, 4, 8, 8), Bounds::new(0, 0, 16, 16))
+        );
+        assert_eq!(
+            FitMode::Contain.apply(8, 16, 16, 16),
+            (Bounds::new(0, 0, 8, 16), Bounds::new(4, 0, 8, 16))
+        );
+        assert_eq!(
+            FitMode::Contain.apply(0, 16, 16, 16),
+            (Bounds::new(0, 0, 0, 16), Bounds::new(0, 0, 16, 16))
+        );
+    }
+
+    #[test]
+    fn test_decode_camera_state() {
+        let bytes = (12.5f32, -3.25f32, 2.0f32).try_to_vec().unwrap();
+        assert_eq!(decode_camera_state(&bytes), Some((12.5, -3.25, 2.0)));
+        assert_eq!(decode_camera_state(&bytes[..8]), None);
+        assert_eq!(
+            decode_camera_state(&[bytes.clone(), vec![0]].concat()),
+            None
+        );
+        let bytes = [f32::NAN, 0., 1.]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(decode_camera_state(&bytes), None);
+    }
+
+    #[test]
+    fn test_zoom_camera_about() {
+        // Zooming in about a point right of center pulls the camera toward it
+        assert_eq!(zoom_camera_about((0., 0., 1.), 10., 0., 2.), (5., 0., 2.));
+        assert_eq!(zoom_camera_about((5., 0., 2.), 10., 0., 1.), (0., 0., 1.));
+        // Zooming about the center only changes the zoom
+        assert_eq!(zoom_camera_about((3., 4., 1.), 3., 4., 8.), (3., 4., 8.));
+        assert_eq!(
+            zoom_camera_about((0., 0., 2.), 10., 0., -1.),
+            (-10., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn test_capture_pixel() {
+        let capture = Capture {
+            width: 2,
+            height: 1,
+            pixels: vec![1, 2, 3, 4, 0xff, 0, 0x80, 0xff],
+        };
+        assert_eq!(capture.pixel(0, 0), 0x01020304);
+        assert_eq!(capture.pixel(1, 0), 0xff0080ff);
+        assert_eq!(capture.pixel(2, 0), 0);
+        assert_eq!(capture.pixel(0, 1), 0);
+        assert_eq!(capture.pixel(-1, 0), 0);
+    }
+
+    #[test]
+    fn test_capture_blit() {
+        let mut capture = Capture {
+            width: 3,
+            height: 2,
+            pixels: vec![0; 24],
+        };
+        // A 2x2 region hanging off the right edge
+        let region = [[1, 1, 1, 1], [2, 2, 2, 2], [3, 3, 3, 3], [4, 4, 4, 4]].concat();
+        capture.blit(2, 0, 2, &region);
+        assert_eq!(capture.pixel(2, 0), 0x01010101);
+        assert_eq!(capture.pixel(2, 1), 0x03030303);
+        assert_eq!(capture.pixel(1, 0), 0);
+        assert_eq!(capture.pixels.iter().filter(|&&b| b != 0).count(), 8);
+    }
+
+    #[test]
+    fn test_reveal_text() {
+        assert_eq!(reveal_text("hello", 0), "");
+        assert_eq!(reveal_text("hello", 3), "hel");
+        assert_eq!(reveal_text("hello", 99), "hello");
+        assert_eq!(reveal_text("héllo", 2), "hé");
+    }
+
+    #[test]
+    fn test_checker_cells() {
+        assert!(checker_cells(Bounds::new(0, 0, 10, 10), 0, false).is_empty());
+        let cells = checker_cells(Bounds::new(5, 5, 10, 6), 4, false);
+        assert_eq!(
+            cells,
+            vec![
+                Bounds::new(9, 5, 4, 4),
+                Bounds::new(5, 9, 4, 2),
+                Bounds::new(13, 9, 2, 2),
+            ]
+        );
+        // World aligned squares are cropped where the bounds cut through them
+        let cells = checker_cells(Bounds::new(-2, 0, 6, 4), 4, true);
+        assert_eq!(cells, vec![Bounds::new(-2, 0, 2, 4)]);
+    }
+
+    #[test]
+    fn test_repeat_tiles_cover_destination() {
+        let tiles = repeat_tiles(10, 5, 4, 4);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(tiles[0], (0, 0, 4, 4));
+        assert_eq!(tiles[2], (8, 0, 2, 4));
+        assert_eq!(tiles[5], (8, 4, 2, 1));
+        let area: u32 = tiles.iter().map(|&(_, _, w, h)| w * h).sum();
+        assert_eq!(area, 50);
+        assert!(repeat_tiles(0, 5, 4, 4).is_empty());
+    }
+
+    #[test]
+    fn test_color_hsv_and_lerp() {
+        assert_eq!(color::rgba(0x12, 0x34, 0x56, 0x78), 0x12345678);
+        assert_eq!(color::with_alpha(0x123456ff, 0x80), 0x12345680);
+        assert_eq!(color::from_hsv(0.0, 1.0, 1.0), 0xff0000ff);
+        assert_eq!(color::from_hsv(120.0, 1.0, 1.0), 0x00ff00ff);
+        assert_eq!(color::from_hsv(360.0, 1.0, 1.0), 0xff0000ff);
+        assert_eq!(color::from_hsv(-120.0, 1.0, 1.0), 0x0000ffff);
+        assert_eq!(color::from_hsv(-0.00001, 1.0, 1.0), 0xff0000ff);
+        assert_eq!(color::from_hsv(200.0, 0.0, 1.0), 0xffffffff);
+        assert_eq!(color::from_hsv(200.0, 1.0, 0.0), 0x000000ff);
+        assert_eq!(color::lerp(0x000000ff, 0xffffff00, 0.5), 0x80808080);
+        assert_eq!(color::lerp(0x000000ff, 0xffffff00, -1.0), 0x000000ff);
+        assert_eq!(color::lerp(0x000000ff, 0xffffff00, 2.0), 0xffffff00);
+    }
+
+    #[test]
+    fn test_flip_mirrors_about_origin() {
+        // A center origin mirrors the rect onto itself rather than shifting it
+        assert_eq!(flip_about_origin(true, 10, 32, Some(16)), (10, 16));
+        // A top-left origin mirrors the rect to the other side of the pivot
+        assert_eq!(flip_about_origin(true, 10, 32, Some(0)), (-22, 32));
+        // The pivot stays at the same point
+        let (x, origin) = flip_about_origin(true, 10, 32, Some(8));
+        assert_eq!(x + origin, 18);
+        assert_eq!((x, x + 32), (2 * 18 - 42, 2 * 18 - 10));
+        // Unflipped or without an origin, the rect is unchanged
+        assert_eq!(flip_about_origin(false, 10, 32, Some(8)), (10, 8));
+        assert_eq!(flip_about_origin(true, 10, 32, None), (10, 0));
+    }
+
+    #[test]
+    fn test_ellipse_spans_have_distinct_extents() {
+        let spans = rotated_ellipse_spans(100, 40, 0)
+            .into_iter()
+            .map(|(_, start, end)| (start as u32, (end - start) as u32))
+            .collect::<Vec<_>>();
+        assert_eq!(spans.len(), 40);
+        let widest = spans.iter().map(|&(_, len)| len).max().unwrap();
+        assert_eq!(widest, 100);
+        // A stadium would be full width until the rounded ends; an ellipse narrows steadily
+        let (start, len) = spans[5];
+        assert!(start > 10 && len < 80);
+        assert_eq!(spans[0].0 + spans[0].1 / 2, 50);
+        assert!(spans.iter().all(|&(start, len)| start + len <= 100));
+    }
+
+    #[test]
+    fn test_rotated_ellipse_rows_are_flush() {
+        // Rotating a quarter turn swaps the extents
+        let spans = rotated_ellipse_spans(100, 40, 90);
+        assert_eq!(spans.first().map(|span| span.0), Some(-30));
+        assert_eq!(spans.last().map(|span| span.0), Some(69));
+        // Every row in between is covered, so there are no seams
+        let spans = rotated_ellipse_spans(100, 40, 30);
+        let rows = spans.iter().map(|span| span.0).collect::<Vec<_>>();
+        assert!(rows.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        // Bordered rows tile the outer span without gaps or overlap
+        let rows = ellipse_rows(100, 40, 4, 30);
+        for (row, start, end) in spans {
+            let mut parts = rows.iter().filter(|part| part.0 == row).collect::<Vec<_>>();
+            parts.sort_by_key(|part| part.1);
+            assert_eq!(parts.first().map(|part| part.1), Some(start));
+            assert_eq!(parts.last().map(|part| part.2), Some(end));
+            assert!(parts.windows(2).all(|pair| pair[0].2 == pair[1].1));
+        }
+    }
 }
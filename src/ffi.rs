@@ -254,6 +254,63 @@ pub mod input {
             mouse(player, out_ptr)
         }
     }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn gamepad_rumble(player: u32, low_freq: f32, high_freq: f32, duration_ms: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        any(feature = "no-host", not(feature = "unstable-host"))
+    ))]
+    pub fn gamepad_rumble(player: u32, low_freq: f32, high_freq: f32, duration_ms: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        not(feature = "no-host"),
+        feature = "unstable-host"
+    ))]
+    pub fn gamepad_rumble(player: u32, low_freq: f32, high_freq: f32, duration_ms: u32) -> i32 {
+        unsafe {
+            #[link(wasm_import_module = "@turbo_genesis/input")]
+            extern "C" {
+                fn gamepad_rumble(
+                    player: u32,
+                    low_freq: f32,
+                    high_freq: f32,
+                    duration_ms: u32,
+                ) -> i32;
+            }
+            gamepad_rumble(player, low_freq, high_freq, duration_ms)
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn gamepad_stop_rumble(player: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        any(feature = "no-host", not(feature = "unstable-host"))
+    ))]
+    pub fn gamepad_stop_rumble(player: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        not(feature = "no-host"),
+        feature = "unstable-host"
+    ))]
+    pub fn gamepad_stop_rumble(player: u32) -> i32 {
+        unsafe {
+            #[link(wasm_import_module = "@turbo_genesis/input")]
+            extern "C" {
+                fn gamepad_stop_rumble(player: u32) -> i32;
+            }
+            gamepad_stop_rumble(player)
+        }
+    }
 }
 
 #[allow(unused)]
@@ -403,6 +460,102 @@ pub mod canvas {
         }
     }
 
+    #[cfg(not(target_family = "wasm"))]
+    pub fn set_clip(x: i32, y: i32, w: u32, h: u32) {}
+    #[cfg(all(
+        target_family = "wasm",
+        any(feature = "no-host", not(feature = "unstable-host"))
+    ))]
+    pub fn set_clip(x: i32, y: i32, w: u32, h: u32) {}
+    #[cfg(all(
+        target_family = "wasm",
+        not(feature = "no-host"),
+        feature = "unstable-host"
+    ))]
+    pub fn set_clip(x: i32, y: i32, w: u32, h: u32) {
+        unsafe {
+            #[link(wasm_import_module = "@turbo_genesis/canvas")]
+            extern "C" {
+                fn set_clip(x: i32, y: i32, w: u32, h: u32);
+            }
+            set_clip(x, y, w, h)
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn reset_clip() {}
+    #[cfg(all(
+        target_family = "wasm",
+        any(feature = "no-host", not(feature = "unstable-host"))
+    ))]
+    pub fn reset_clip() {}
+    #[cfg(all(
+        target_family = "wasm",
+        not(feature = "no-host"),
+        feature = "unstable-host"
+    ))]
+    pub fn reset_clip() {
+        unsafe {
+            #[link(wasm_import_module = "@turbo_genesis/canvas")]
+            extern "C" {
+                fn reset_clip();
+            }
+            reset_clip()
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn read_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *mut u8, len: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        any(feature = "no-host", not(feature = "unstable-host"))
+    ))]
+    pub fn read_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *mut u8, len: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        not(feature = "no-host"),
+        feature = "unstable-host"
+    ))]
+    pub fn read_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *mut u8, len: u32) -> i32 {
+        unsafe {
+            #[link(wasm_import_module = "@turbo_genesis/canvas")]
+            extern "C" {
+                fn read_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *mut u8, len: u32) -> i32;
+            }
+            read_pixels(x, y, w, h, ptr, len)
+        }
+    }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn write_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *const u8, len: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        any(feature = "no-host", not(feature = "unstable-host"))
+    ))]
+    pub fn write_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *const u8, len: u32) -> i32 {
+        -1
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        not(feature = "no-host"),
+        feature = "unstable-host"
+    ))]
+    pub fn write_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *const u8, len: u32) -> i32 {
+        unsafe {
+            #[link(wasm_import_module = "@turbo_genesis/canvas")]
+            extern "C" {
+                fn write_pixels(x: i32, y: i32, w: u32, h: u32, ptr: *const u8, len: u32) -> i32;
+            }
+            write_pixels(x, y, w, h, ptr, len)
+        }
+    }
+
     #[cfg(not(target_family = "wasm"))]
     pub fn circfill(x: i32, y: i32, d: u32, fill: u32) {}
     #[cfg(all(target_family = "wasm", feature = "no-host"))]
@@ -653,4 +806,103 @@ pub mod canvas {
             );
         }
     }
+
+    #[cfg(not(target_family = "wasm"))]
+    pub fn draw_quad3(
+        dest_xy: u64,
+        dest_wh: u64,
+        sprite_xy: u64,
+        sprite_wh: u64,
+        sprite_xy_offset: u64,
+        fill_ab: u64,
+        border_radius: u32,
+        border_size: u32,
+        border_color: u32,
+        origin_xy: u64,
+        rotation_deg: i32,
+        flags: u32,
+        tint_color: u32,
+        tint_strength: f32,
+    ) {
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        any(feature = "no-host", not(feature = "unstable-host"))
+    ))]
+    pub fn draw_quad3(
+        dest_xy: u64,
+        dest_wh: u64,
+        sprite_xy: u64,
+        sprite_wh: u64,
+        sprite_xy_offset: u64,
+        fill_ab: u64,
+        border_radius: u32,
+        border_size: u32,
+        border_color: u32,
+        origin_xy: u64,
+        rotation_deg: i32,
+        flags: u32,
+        tint_color: u32,
+        tint_strength: f32,
+    ) {
+    }
+    #[cfg(all(
+        target_family = "wasm",
+        not(feature = "no-host"),
+        feature = "unstable-host"
+    ))]
+    pub fn draw_quad3(
+        dest_xy: u64,
+        dest_wh: u64,
+        sprite_xy: u64,
+        sprite_wh: u64,
+        sprite_xy_offset: u64,
+        fill_ab: u64,
+        border_radius: u32,
+        border_size: u32,
+        border_color: u32,
+        origin_xy: u64,
+        rotation_deg: i32,
+        flags: u32,
+        tint_color: u32,
+        tint_strength: f32,
+    ) {
+        unsafe {
+            #[link(wasm_import_module = "@turbo_genesis/canvas")]
+            extern "C" {
+                fn draw_quad3(
+                    dest_xy: u64,
+                    dest_wh: u64,
+                    sprite_xy: u64,
+                    sprite_wh: u64,
+                    sprite_xy_offset: u64,
+                    fill_ab: u64,
+                    border_radius: u32,
+                    border_size: u32,
+                    border_color: u32,
+                    origin_xy: u64,
+                    rotation_deg: i32,
+                    flags: u32,
+                    tint_color: u32,
+                    tint_strength: f32,
+                );
+            }
+            draw_quad3(
+                dest_xy,
+                dest_wh,
+                sprite_xy,
+                sprite_wh,
+                sprite_xy_offset,
+                fill_ab,
+                border_radius,
+                border_size,
+                border_color,
+                origin_xy,
+                rotation_deg,
+                flags,
+                tint_color,
+                tint_strength,
+            )
+        }
+    }
 }
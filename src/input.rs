@@ -1,19 +1,590 @@
 use crate::{cam, canvas_size, ffi};
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::collections::BTreeMap;
 
 pub fn gamepad(player: u32) -> Gamepad<Button> {
-    let data = &mut [0; std::mem::size_of::<Gamepad<u8>>()];
-    ffi::input::gamepad(player.into(), data.as_mut_ptr());
-    let gamepad: Gamepad<u8> = *bytemuck::from_bytes(data);
+    let gamepad = replay::input(player).gamepad;
     gamepad.into()
 }
 
-pub fn mouse(player: u32) -> Mouse<Button> {
+// Reads a player's gamepad and mouse from the host
+fn live_input(player: u32) -> PlayerInput<u8> {
+    let data = &mut [0; std::mem::size_of::<Gamepad<u8>>()];
+    ffi::input::gamepad(player, data.as_mut_ptr());
+    let gamepad: Gamepad<u8> = *bytemuck::from_bytes(data);
     let data = &mut [0; std::mem::size_of::<Mouse<u8>>()];
-    ffi::input::mouse(player.into(), data.as_mut_ptr());
+    ffi::input::mouse(player, data.as_mut_ptr());
     let mouse: Mouse<u8> = *bytemuck::from_bytes(data);
+    PlayerInput { gamepad, mouse }
+}
+
+pub mod gamepad {
+    use super::{Button, GamepadButton};
+    use crate::ffi;
+
+    /// Checks if a gamepad button was pressed this frame.
+    pub fn just_pressed(player: u32, button: GamepadButton) -> bool {
+        !super::is_first_frame() && super::gamepad(player).button(button) == Button::JustPressed
+    }
+
+    /// Checks if a gamepad button was released this frame.
+    pub fn just_released(player: u32, button: GamepadButton) -> bool {
+        !super::is_first_frame() && super::gamepad(player).button(button) == Button::JustReleased
+    }
+
+    /// Starts rumbling a player's gamepad. Motor strengths are clamped to 0.0-1.0 and a
+    /// `duration_ms` of 0 stops the rumble. Returns false if the gamepad has no haptics.
+    pub fn rumble(player: u32, low_freq: f32, high_freq: f32, duration_ms: u32) -> bool {
+        match rumble_command(low_freq, high_freq, duration_ms) {
+            Rumble::Start(low_freq, high_freq, duration_ms) => {
+                ffi::input::gamepad_rumble(player, low_freq, high_freq, duration_ms) == 0
+            }
+            Rumble::Stop => stop_rumble(player),
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub(crate) enum Rumble {
+        Start(f32, f32, u32),
+        Stop,
+    }
+
+    // Motor strengths are clamped to 0.0-1.0 with NaN treated as off
+    pub(crate) fn rumble_command(low_freq: f32, high_freq: f32, duration_ms: u32) -> Rumble {
+        let strength = |value: f32| {
+            if value.is_nan() {
+                0.
+            } else {
+                value.clamp(0., 1.)
+            }
+        };
+        match duration_ms {
+            0 => Rumble::Stop,
+            _ => Rumble::Start(strength(low_freq), strength(high_freq), duration_ms),
+        }
+    }
+
+    /// Stops any active rumble on a player's gamepad.
+    pub fn stop_rumble(player: u32) -> bool {
+        ffi::input::gamepad_stop_rumble(player) == 0
+    }
+}
+
+pub fn mouse(player: u32) -> Mouse<Button> {
+    let mouse = replay::input(player).mouse;
     mouse.into()
 }
 
+pub mod mouse {
+    use super::{Button, Mouse, MouseButton};
+    use std::collections::BTreeMap;
+
+    // Max time in milliseconds between presses of a double-click
+    pub const DOUBLE_CLICK_MS: u32 = 300;
+    // Max distance in pixels between presses of a double-click
+    pub const DOUBLE_CLICK_DISTANCE: u32 = 4;
+
+    #[derive(Debug, Default, Clone, Copy)]
+    pub(crate) struct PointerState {
+        pub(crate) tick: Option<usize>,
+        pub(crate) press_start: Option<[i32; 2]>,
+        pub(crate) dragging: bool,
+        pub(crate) last_press: Option<(usize, [i32; 2])>,
+        pub(crate) double_click: bool,
+        pub(crate) click: bool,
+    }
+
+    static mut POINTER_STATES: BTreeMap<u32, PointerState> = BTreeMap::new();
+
+    fn distance([ax, ay]: [i32; 2], [bx, by]: [i32; 2]) -> u32 {
+        let dx = (ax - bx) as f32;
+        let dy = (ay - by) as f32;
+        (dx * dx + dy * dy).sqrt() as u32
+    }
+
+    // Updates the left button's press/drag/click tracking once per tick.
+    // This is lazy, so the queries below need to be called every frame.
+    fn pointer_state(player: u32) -> &'static mut PointerState {
+        let states = unsafe { &mut *std::ptr::addr_of_mut!(POINTER_STATES) };
+        let state = states.entry(player).or_default();
+        let tick = crate::sys::tick();
+        if state.tick != Some(tick) {
+            let mut mouse = super::mouse(player);
+            // A held button may read as just pressed on the first frame after a reload
+            if super::is_first_frame() && mouse.left.just_pressed() {
+                mouse.left = Button::Pressed;
+            }
+            step(state, &mouse, tick);
+        }
+        state
+    }
+
+    // The double-click window in ticks at the given frame rate, rounded to the nearest tick
+    pub(crate) fn double_click_ticks(frame_rate: u32) -> usize {
+        ((DOUBLE_CLICK_MS as usize * frame_rate as usize) + 500) / 1000
+    }
+
+    // Advances the pointer state to `tick`. If queries skipped a frame, a press or
+    // release may have been missed, so tracking starts over.
+    pub(crate) fn step(state: &mut PointerState, mouse: &Mouse<Button>, tick: usize) {
+        if state.tick.is_some_and(|last| tick.saturating_sub(last) > 1) {
+            *state = PointerState::default();
+        }
+        state.tick = Some(tick);
+        state.double_click = false;
+        state.click = false;
+        if mouse.left.just_pressed() {
+            let position = mouse.position;
+            let window = double_click_ticks(crate::sys::time::frame_rate());
+            state.double_click = state.last_press.is_some_and(|(last_tick, last_position)| {
+                tick.saturating_sub(last_tick) <= window
+                    && distance(position, last_position) <= DOUBLE_CLICK_DISTANCE
+            });
+            // A third press starts a new pair rather than forming another double-click
+            state.last_press = if state.double_click {
+                None
+            } else {
+                Some((tick, position))
+            };
+            state.click = !state.double_click;
+            state.press_start = Some(position);
+        }
+        if !mouse.left.pressed() {
+            state.press_start = None;
+            state.dragging = false;
+        }
+    }
+
+    /// Returns the start and current position of an in-progress left button drag.
+    /// A drag begins once the pointer moves more than `threshold` pixels from where
+    /// it was pressed, and ends when the button is released.
+    pub fn drag(player: u32, threshold: u32) -> Option<([i32; 2], [i32; 2])> {
+        let current = super::mouse(player).position;
+        drag_to(pointer_state(player), current, threshold)
+    }
+
+    pub(crate) fn drag_to(
+        state: &mut PointerState,
+        current: [i32; 2],
+        threshold: u32,
+    ) -> Option<([i32; 2], [i32; 2])> {
+        let start = state.press_start?;
+        if !state.dragging && distance(start, current) > threshold {
+            state.dragging = true;
+        }
+        state.dragging.then_some((start, current))
+    }
+
+    /// Checks if the left button was pressed this frame as the second half of a double-click.
+    pub fn double_click(player: u32) -> bool {
+        pointer_state(player).double_click
+    }
+
+    /// Checks if the left button was pressed this frame, excluding the second
+    /// press of a double-click.
+    pub fn click(player: u32) -> bool {
+        pointer_state(player).click
+    }
+
+    /// Gets the horizontal and vertical wheel movement since the last frame.
+    /// Trackpad scrolling is reported through the same wheel delta, which the
+    /// host resets each frame rather than accumulating.
+    pub fn scroll_delta(player: u32) -> (f32, f32) {
+        let [x, y] = super::mouse(player).wheel;
+        (x as f32, y as f32)
+    }
+
+    /// Checks if a mouse button was pressed this frame.
+    pub fn just_pressed(player: u32, button: MouseButton) -> bool {
+        !super::is_first_frame() && super::mouse(player).button(button) == Button::JustPressed
+    }
+
+    /// Checks if a mouse button was released this frame.
+    pub fn just_released(player: u32, button: MouseButton) -> bool {
+        !super::is_first_frame() && super::mouse(player).button(button) == Button::JustReleased
+    }
+}
+
+pub mod replay {
+    use super::PlayerInput;
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use std::collections::BTreeMap;
+
+    // Inputs by frame and player, where frames count ticks from the start
+    type Frames = BTreeMap<(u32, u32), PlayerInput<u8>>;
+
+    enum Replay {
+        Recording {
+            start: usize,
+            frames: Frames,
+        },
+        Playing {
+            start: usize,
+            len: u32,
+            frames: Frames,
+        },
+    }
+
+    static mut REPLAY: Option<Replay> = None;
+
+    fn replay() -> &'static mut Option<Replay> {
+        unsafe { &mut *std::ptr::addr_of_mut!(REPLAY) }
+    }
+
+    #[derive(BorshSerialize, BorshDeserialize)]
+    struct Recording {
+        len: u32,
+        inputs: Vec<(u32, u32, Vec<u8>)>,
+    }
+
+    /// Starts recording input, replacing any recording or playback in progress.
+    /// A player's gamepad and mouse are captured together the first time either is
+    /// read in a frame, and later reads in that frame return the same input.
+    pub fn start_recording() {
+        start_recording_at(crate::sys::tick())
+    }
+
+    /// Stops recording and returns the serialized recording. Returns an empty
+    /// recording if none was in progress.
+    pub fn stop_recording() -> Vec<u8> {
+        stop_recording_at(crate::sys::tick())
+    }
+
+    /// Replays a recording from this frame, in place of live input. Live input
+    /// resumes once the recording ends. Returns false if it can't be decoded.
+    pub fn play(recording: &[u8]) -> bool {
+        play_at(recording, crate::sys::tick())
+    }
+
+    /// Stops recording or playback and returns to live input.
+    pub fn stop() {
+        *replay() = None;
+    }
+
+    pub fn is_recording() -> bool {
+        matches!(replay(), Some(Replay::Recording { .. }))
+    }
+
+    pub fn is_playing() -> bool {
+        matches!(replay(), Some(Replay::Playing { .. }))
+    }
+
+    pub(crate) fn start_recording_at(tick: usize) {
+        *replay() = Some(Replay::Recording {
+            start: tick,
+            frames: Frames::new(),
+        });
+    }
+
+    pub(crate) fn stop_recording_at(tick: usize) -> Vec<u8> {
+        let (len, frames) = match replay().take() {
+            Some(Replay::Recording { start, frames }) => {
+                (tick.saturating_sub(start) as u32 + 1, frames)
+            }
+            other => {
+                *replay() = other;
+                (0, Frames::new())
+            }
+        };
+        let inputs = frames
+            .into_iter()
+            .map(|((frame, player), input)| (frame, player, bytemuck::bytes_of(&input).to_vec()))
+            .collect();
+        Recording { len, inputs }.try_to_vec().unwrap_or_default()
+    }
+
+    pub(crate) fn play_at(recording: &[u8], tick: usize) -> bool {
+        let Ok(Recording { len, inputs }) = Recording::try_from_slice(recording) else {
+            return false;
+        };
+        let mut frames = Frames::new();
+        for (frame, player, bytes) in inputs {
+            let Ok(input) = bytemuck::try_pod_read_unaligned(&bytes) else {
+                return false;
+            };
+            frames.insert((frame, player), input);
+        }
+        *replay() = Some(Replay::Playing {
+            start: tick,
+            len,
+            frames,
+        });
+        true
+    }
+
+    pub(crate) fn input(player: u32) -> PlayerInput<u8> {
+        input_at(crate::sys::tick(), player, super::live_input)
+    }
+
+    pub(crate) fn input_at(
+        tick: usize,
+        player: u32,
+        live: fn(u32) -> PlayerInput<u8>,
+    ) -> PlayerInput<u8> {
+        let state = replay();
+        match state {
+            Some(Replay::Recording { start, frames }) => {
+                let frame = tick.saturating_sub(*start) as u32;
+                *frames
+                    .entry((frame, player))
+                    .or_insert_with(|| live(player))
+            }
+            Some(Replay::Playing { start, len, frames }) => {
+                match tick.checked_sub(*start).map(|frame| frame as u32) {
+                    // Players that weren't read while recording have no input
+                    Some(frame) if frame < *len => frames
+                        .get(&(frame, player))
+                        .copied()
+                        .unwrap_or_else(bytemuck::Zeroable::zeroed),
+                    _ => {
+                        *state = None;
+                        live(player)
+                    }
+                }
+            }
+            None => live(player),
+        }
+    }
+}
+
+pub mod bindings {
+    use super::{Button, GamepadButton, MouseButton};
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use std::collections::BTreeMap;
+
+    // Key the bindings are saved under in local storage
+    const STORAGE_KEY: &str = "turbo::input::bindings";
+
+    /// A physical input that can trigger an action.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+    pub enum Binding {
+        Gamepad { player: u32, button: GamepadButton },
+        Mouse { player: u32, button: MouseButton },
+    }
+    impl Binding {
+        pub fn state(&self) -> Button {
+            match *self {
+                Self::Gamepad { player, button } => super::gamepad(player).button(button),
+                Self::Mouse { player, button } => super::mouse(player).button(button),
+            }
+        }
+    }
+
+    static mut BINDINGS: BTreeMap<String, Vec<Binding>> = BTreeMap::new();
+
+    fn bindings() -> &'static mut BTreeMap<String, Vec<Binding>> {
+        unsafe { &mut *std::ptr::addr_of_mut!(BINDINGS) }
+    }
+
+    /// Maps a physical input to an action. An action can have any number of inputs.
+    pub fn bind(action: &str, binding: Binding) {
+        let inputs = bindings().entry(action.to_string()).or_default();
+        if !inputs.contains(&binding) {
+            inputs.push(binding);
+        }
+    }
+
+    /// Removes all inputs mapped to an action.
+    pub fn unbind(action: &str) {
+        bindings().remove(action);
+    }
+
+    /// Returns the inputs mapped to an action.
+    pub fn get(action: &str) -> Vec<Binding> {
+        bindings().get(action).cloned().unwrap_or_default()
+    }
+
+    /// Saves all bindings to local storage.
+    pub fn save() -> bool {
+        crate::sys::local::set(STORAGE_KEY, bindings())
+    }
+
+    /// Replaces all bindings with the ones saved in local storage, if any.
+    pub fn load() -> bool {
+        match crate::sys::local::get(STORAGE_KEY) {
+            Some(saved) => {
+                *bindings() = saved;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // An action is held while any of its inputs are held, so it only changes state
+    // when the first input is pressed or the last one is released.
+    pub(crate) fn combine(states: impl IntoIterator<Item = Button>) -> Button {
+        let (mut now, mut before) = (false, false);
+        for state in states {
+            now |= state.pressed();
+            before |= state == Button::Pressed || state == Button::JustReleased;
+        }
+        match (now, before) {
+            (true, true) => Button::Pressed,
+            (true, false) => Button::JustPressed,
+            (false, true) => Button::JustReleased,
+            (false, false) => Button::Released,
+        }
+    }
+
+    /// Returns the combined state of an action's inputs. Unbound actions are released.
+    pub fn action(action: &str) -> Button {
+        match bindings().get(action) {
+            Some(inputs) => combine(inputs.iter().map(Binding::state)),
+            None => Button::Released,
+        }
+    }
+}
+
+/// Checks if any input bound to an action is held.
+pub fn action_pressed(action: &str) -> bool {
+    bindings::action(action).pressed()
+}
+
+/// Checks if an action was pressed this frame.
+pub fn action_just_pressed(action: &str) -> bool {
+    !is_first_frame() && bindings::action(action).just_pressed()
+}
+
+/// Checks if an action was released this frame.
+pub fn action_just_released(action: &str) -> bool {
+    !is_first_frame() && bindings::action(action).just_released()
+}
+
+/// A kind of input device, for choosing which button prompts to show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputDevice {
+    Gamepad,
+    Mouse,
+}
+
+/// Checks if any gamepad or mouse button is held.
+pub fn any_pressed(player: u32) -> bool {
+    let (gamepad, mouse) = (gamepad(player), mouse(player));
+    any_button(&gamepad, &mouse, Button::pressed)
+}
+
+/// Checks if any gamepad or mouse button was pressed this frame.
+pub fn any_just_pressed(player: u32) -> bool {
+    let (gamepad, mouse) = (gamepad(player), mouse(player));
+    !is_first_frame() && any_button(&gamepad, &mouse, Button::just_pressed)
+}
+
+fn any_button(gamepad: &Gamepad<Button>, mouse: &Mouse<Button>, f: fn(&Button) -> bool) -> bool {
+    GamepadButton::ALL.iter().any(|&b| f(&gamepad.button(b)))
+        || [MouseButton::Left, MouseButton::Right]
+            .iter()
+            .any(|&b| f(&mouse.button(b)))
+}
+
+/// Returns the device a player last used, or `None` before any input. This only
+/// changes on a button press or pointer movement, and is tracked lazily, so call
+/// it every frame.
+pub fn last_device(player: u32) -> Option<InputDevice> {
+    // Tick, last pointer position, and device for each player
+    static mut LAST_DEVICES: BTreeMap<u32, (usize, [i32; 2], Option<InputDevice>)> =
+        BTreeMap::new();
+    let states = unsafe { &mut *std::ptr::addr_of_mut!(LAST_DEVICES) };
+    let tick = crate::sys::tick();
+    let (gamepad, mouse) = (gamepad(player), mouse(player));
+    let (last_tick, position, device) =
+        states.entry(player).or_insert((tick, mouse.position, None));
+    if *last_tick != tick {
+        *device = detect_device(&gamepad, &mouse, *position).or(*device);
+        *last_tick = tick;
+        *position = mouse.position;
+    }
+    *device
+}
+
+// Button presses win over pointer movement made in the same frame
+fn detect_device(
+    gamepad: &Gamepad<Button>,
+    mouse: &Mouse<Button>,
+    prev_position: [i32; 2],
+) -> Option<InputDevice> {
+    let (wheel, position) = (mouse.wheel, mouse.position);
+    if GamepadButton::ALL
+        .iter()
+        .any(|&b| gamepad.button(b).just_pressed())
+    {
+        Some(InputDevice::Gamepad)
+    } else if mouse.left.just_pressed()
+        || mouse.right.just_pressed()
+        || wheel != [0, 0]
+        || position != prev_position
+    {
+        Some(InputDevice::Mouse)
+    } else {
+        None
+    }
+}
+
+/// Checks if a gamepad button was pressed this frame.
+pub fn just_pressed(player: u32, button: GamepadButton) -> bool {
+    gamepad::just_pressed(player, button)
+}
+
+/// Checks if a gamepad button was released this frame.
+pub fn just_released(player: u32, button: GamepadButton) -> bool {
+    gamepad::just_released(player, button)
+}
+
+// The host advances button states once per frame, so a press is only ever
+// JustPressed for a single frame. The one exception is the first frame after a
+// (hot) reload, where a held button may still read as JustPressed.
+fn is_first_frame() -> bool {
+    is_first_frame_at(crate::sys::tick())
+}
+
+// Tick of the first frame since the module was loaded, recorded by `go!`
+static mut FIRST_TICK: Option<usize> = None;
+
+// Called at the start of every frame. Only the first call is kept.
+pub(crate) fn record_frame(tick: usize) {
+    let first_tick = unsafe { &mut *std::ptr::addr_of_mut!(FIRST_TICK) };
+    first_tick.get_or_insert(tick);
+}
+
+pub(crate) fn is_first_frame_at(tick: usize) -> bool {
+    unsafe { *std::ptr::addr_of!(FIRST_TICK) == Some(tick) }
+}
+
+/// Identifies a single gamepad button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum GamepadButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    B,
+    X,
+    Y,
+    Start,
+    Select,
+}
+impl GamepadButton {
+    pub const ALL: [Self; 10] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::A,
+        Self::B,
+        Self::X,
+        Self::Y,
+        Self::Start,
+        Self::Select,
+    ];
+}
+
+/// Identifies a single mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+}
+
 /// Represents the state of an input (controller or mouse button) at a given moment.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Button {
@@ -103,6 +674,13 @@ pub struct Mouse<T: Copy> {
 }
 
 impl Mouse<Button> {
+    /// Gets the state of a mouse button.
+    pub fn button(&self, button: MouseButton) -> Button {
+        match button {
+            MouseButton::Left => self.left,
+            MouseButton::Right => self.right,
+        }
+    }
     pub fn intersects(&self, x: i32, y: i32, w: u32, h: u32) -> bool {
         let [mx, my] = self.position;
         let x0 = x;
@@ -174,6 +752,24 @@ pub struct Gamepad<T: Copy> {
     pub select: T,
 }
 
+impl Gamepad<Button> {
+    /// Gets the state of a gamepad button.
+    pub fn button(&self, button: GamepadButton) -> Button {
+        match button {
+            GamepadButton::Up => self.up,
+            GamepadButton::Down => self.down,
+            GamepadButton::Left => self.left,
+            GamepadButton::Right => self.right,
+            GamepadButton::A => self.a,
+            GamepadButton::B => self.b,
+            GamepadButton::X => self.x,
+            GamepadButton::Y => self.y,
+            GamepadButton::Start => self.start,
+            GamepadButton::Select => self.select,
+        }
+    }
+}
+
 impl Into<Gamepad<Button>> for Gamepad<u8> {
     fn into(self) -> Gamepad<Button> {
         Gamepad {
@@ -300,6 +896,108 @@ impl PlayerInput<Button> {
 mod tests {
     use super::*;
 
+    // Mouse input with only the left button and position set
+    fn mouse_input(left: Button, position: [i32; 2]) -> Mouse<Button> {
+        Mouse {
+            left,
+            right: Button::Released,
+            wheel: [0, 0],
+            position,
+        }
+    }
+
+    #[test]
+    fn test_pointer_press_drag_release() {
+        use mouse::{drag_to, step, PointerState};
+        let mut state = PointerState::default();
+        step(&mut state, &mouse_input(Button::JustPressed, [10, 10]), 1);
+        assert!(state.click);
+        // Small moves stay under the threshold
+        step(&mut state, &mouse_input(Button::Pressed, [12, 10]), 2);
+        assert!(!state.click);
+        assert_eq!(drag_to(&mut state, [12, 10], 4), None);
+        step(&mut state, &mouse_input(Button::Pressed, [20, 10]), 3);
+        assert_eq!(drag_to(&mut state, [20, 10], 4), Some(([10, 10], [20, 10])));
+        // The drag continues even if the pointer comes back near the start
+        assert_eq!(drag_to(&mut state, [11, 10], 4), Some(([10, 10], [11, 10])));
+        step(&mut state, &mouse_input(Button::JustReleased, [20, 10]), 4);
+        assert_eq!(drag_to(&mut state, [20, 10], 4), None);
+    }
+
+    #[test]
+    fn test_pointer_double_click_timing() {
+        use mouse::{double_click_ticks, step, PointerState};
+        let input = |left| mouse_input(left, [5, 5]);
+        let click_at = |state: &mut PointerState, tick: usize| {
+            step(state, &input(Button::JustPressed), tick);
+            step(state, &input(Button::JustReleased), tick + 1);
+        };
+        let mut state = PointerState::default();
+        click_at(&mut state, 10);
+        step(&mut state, &input(Button::JustPressed), 12);
+        assert!(state.double_click && !state.click);
+        // A third press starts a new pair
+        step(&mut state, &input(Button::JustReleased), 13);
+        step(&mut state, &input(Button::JustPressed), 14);
+        assert!(state.click && !state.double_click);
+        // Presses too far apart are two clicks
+        let mut state = PointerState::default();
+        step(&mut state, &input(Button::JustPressed), 20);
+        let late = 21 + double_click_ticks(crate::sys::time::frame_rate()) + 1;
+        for tick in 21..late {
+            step(&mut state, &input(Button::Released), tick);
+        }
+        step(&mut state, &input(Button::JustPressed), late);
+        assert!(state.click && !state.double_click);
+    }
+
+    #[test]
+    fn test_double_click_window_follows_frame_rate() {
+        use mouse::double_click_ticks;
+        assert_eq!(double_click_ticks(60), 18);
+        assert_eq!(double_click_ticks(30), 9);
+        assert_eq!(double_click_ticks(144), 43);
+    }
+
+    #[test]
+    fn test_pointer_state_resets_after_skipped_frames() {
+        use mouse::{step, PointerState};
+        let input = |left| mouse_input(left, [5, 5]);
+        let mut state = PointerState::default();
+        step(&mut state, &input(Button::JustPressed), 1);
+        // Two frames pass without a query, so the release may have been missed
+        step(&mut state, &input(Button::Pressed), 3);
+        assert_eq!(state.press_start, None);
+        assert_eq!(state.last_press, None);
+        // Back to back presses after the gap aren't a double-click
+        step(&mut state, &input(Button::JustPressed), 4);
+        assert!(state.click);
+    }
+
+    #[test]
+    fn test_first_frame_is_recorded_by_frame() {
+        record_frame(5);
+        // Later frames don't replace the first one
+        record_frame(6);
+        assert!(is_first_frame_at(5));
+        assert!(!is_first_frame_at(6));
+    }
+
+    #[test]
+    fn test_rumble_command() {
+        use gamepad::{rumble_command, Rumble};
+        assert_eq!(
+            rumble_command(0.5, 0.25, 100),
+            Rumble::Start(0.5, 0.25, 100)
+        );
+        assert_eq!(rumble_command(-1., 2., 100), Rumble::Start(0., 1., 100));
+        assert_eq!(
+            rumble_command(f32::NAN, 0.5, 100),
+            Rumble::Start(0., 0.5, 100)
+        );
+        assert_eq!(rumble_command(1., 1., 0), Rumble::Stop);
+    }
+
     #[test]
     fn test_input_state_transitions() {
         // Test next() method of Button enum
@@ -329,6 +1027,86 @@ mod tests {
         assert!(state.released());
     }
 
+    #[test]
+    fn test_action_bindings_combine_inputs() {
+        use Button::*;
+        assert_eq!(bindings::combine([JustPressed, Released]), JustPressed);
+        // Pressing a second input while the first is held isn't a new press
+        assert_eq!(bindings::combine([Pressed, JustPressed]), Pressed);
+        // Releasing one of two held inputs doesn't release the action
+        assert_eq!(bindings::combine([JustReleased, Pressed]), Pressed);
+        assert_eq!(bindings::combine([JustReleased, Released]), JustReleased);
+        assert_eq!(bindings::combine([]), Released);
+
+        assert!(!action_pressed("unbound"));
+        assert!(!action_just_pressed("unbound"));
+        let jump = bindings::Binding::Gamepad {
+            player: 0,
+            button: GamepadButton::A,
+        };
+        bindings::bind("jump", jump);
+        bindings::bind("jump", jump);
+        assert_eq!(bindings::get("jump"), vec![jump]);
+        bindings::unbind("jump");
+        assert!(bindings::get("jump").is_empty());
+    }
+
+    #[test]
+    fn test_detect_input_device() {
+        let mut input = PlayerInput::new();
+        let mut mouse = input.mouse;
+        mouse.position = [5, 5];
+        assert_eq!(detect_device(&input.gamepad, &mouse, [5, 5]), None);
+        // Held buttons aren't new input
+        input.gamepad.a = Button::Pressed;
+        assert_eq!(detect_device(&input.gamepad, &mouse, [5, 5]), None);
+        assert_eq!(
+            detect_device(&input.gamepad, &mouse, [4, 5]),
+            Some(InputDevice::Mouse)
+        );
+        input.gamepad.b = Button::JustPressed;
+        assert_eq!(
+            detect_device(&input.gamepad, &mouse, [4, 5]),
+            Some(InputDevice::Gamepad)
+        );
+        assert!(any_button(&input.gamepad, &mouse, Button::just_pressed));
+        assert!(!any_button(&input.gamepad, &mouse, Button::just_released));
+    }
+
+    #[test]
+    fn test_replay_round_trip() {
+        fn live_a(_: u32) -> PlayerInput<u8> {
+            let mut input = PlayerInput::new();
+            input.gamepad.a = Button::Pressed;
+            input.into()
+        }
+        fn live_b(_: u32) -> PlayerInput<u8> {
+            let mut input = PlayerInput::new();
+            input.mouse.position = [3, 4];
+            input.into()
+        }
+        fn live_idle(_: u32) -> PlayerInput<u8> {
+            PlayerInput::new().into()
+        }
+        replay::start_recording_at(10);
+        assert_eq!(replay::input_at(10, 0, live_a), live_a(0));
+        // Reads within a frame are consistent
+        assert_eq!(replay::input_at(10, 0, live_b), live_a(0));
+        assert_eq!(replay::input_at(11, 0, live_b), live_b(0));
+        let recording = replay::stop_recording_at(11);
+        assert!(!replay::is_recording());
+
+        assert!(replay::play_at(&recording, 100));
+        assert_eq!(replay::input_at(100, 0, live_idle), live_a(0));
+        assert_eq!(replay::input_at(100, 1, live_a), live_idle(1));
+        assert_eq!(replay::input_at(101, 0, live_idle), live_b(0));
+        assert!(replay::is_playing());
+        // Live input resumes once the recording runs out
+        assert_eq!(replay::input_at(102, 0, live_a), live_a(0));
+        assert!(!replay::is_playing());
+        assert!(!replay::play_at(&[1, 2], 0));
+    }
+
     #[test]
     fn test_user_input_main_events_cleared() {
         let mut user_input = PlayerInput::new();
@@ -1,6 +1,7 @@
 pub(crate) mod ffi;
 pub(crate) mod json;
 
+pub mod bounds;
 pub mod canvas;
 pub mod http;
 pub mod input;
@@ -11,12 +12,21 @@ pub mod tween;
 #[cfg(feature = "solana")]
 pub mod solana;
 
+// Leveled logging macros, filtered with `sys::console::set_level`
+pub mod log {
+    pub use crate::log_debug as debug;
+    pub use crate::log_error as error;
+    pub use crate::log_info as info;
+    pub use crate::log_warn as warn;
+}
+
 pub use binary_layout;
 pub use borsh;
 pub use paste;
 pub use structstruck;
 
 pub mod prelude {
+    pub use crate::bounds::*;
     pub use crate::canvas::*;
     pub use crate::input::*;
     pub use crate::println;
@@ -34,7 +44,7 @@ pub fn run_snapshot(snapshot_data: &[u8], run: impl FnOnce()) -> Vec<u8> {
 
 #[macro_export]
 macro_rules! println {
-    ($fmt:expr $(, $($arg:tt)*)?) => { $crate::sys::log(&format!($fmt, $($($arg)*)?)) };
+    ($fmt:expr $(, $($arg:tt)*)?) => { $crate::sys::log_args(format_args!($fmt, $($($arg)*)?)) };
 }
 
 #[macro_export]
@@ -49,22 +59,74 @@ macro_rules! cfg {
     };
 }
 
+/// Exports a TOML config like `cfg!` and declares a struct that reads it back.
+///
+/// ```ignore
+/// config_struct!(struct Config { resolution: (u32, u32), title: String } = CONFIG_TOML);
+/// let config = Config::load()?;
+/// ```
+///
+/// Fields are read by key, with keys under a `[section]` named `section_key`.
+/// The macro also emits a test that loads the struct, so `cargo test` fails if a
+/// field no longer matches the TOML. Needs the `config` feature.
+#[cfg(feature = "config")]
 #[macro_export]
-macro_rules! init {
-    (struct $StructName:ident { $($fields:tt)* } = $default:expr) => {
-        use $crate::prelude::{*, println};
-        use $crate::borsh::{self, *};
-        use $crate::structstruck::{self, *};
-        strike! {
-            #[strikethrough[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]]
-            struct $StructName {
-                $($fields)*
+macro_rules! config_struct {
+    (struct $StructName:ident { $($field:ident : $ty:ty),* $(,)? } = $toml:expr) => {
+        $crate::cfg!($StructName::TOML);
+        #[derive(Debug, Clone)]
+        pub struct $StructName {
+            $(pub $field: $ty,)*
+        }
+        impl $StructName {
+            pub const TOML: &'static str = $toml;
+            pub fn load() -> Result<Self, std::io::Error> {
+                let values = $crate::sys::config::parse(Self::TOML)?;
+                Ok(Self {
+                    $($field: $crate::sys::config::field(&values, stringify!($field))?,)*
+                })
             }
         }
+        $crate::paste::paste! {
+            #[cfg(test)]
+            #[test]
+            fn [<$StructName:snake _matches_toml>]() {
+                if let Err(err) = $StructName::load() {
+                    panic!("{} doesn't match its TOML: {}", stringify!($StructName), err);
+                }
+            }
+        }
+    };
+}
+
+/// Declares the game state struct along with `default`, `load`, and `save`.
+///
+/// The `= default` expression only runs when no saved state is restored, so it
+/// is the place for one-time setup. It is not re-run when a hot-reload restores
+/// existing state. Append `, versioned` to save with a `sys::HotState` version.
+#[macro_export]
+macro_rules! init {
+    (struct $StructName:ident { $($fields:tt)* } = $default:expr, versioned) => {
+        $crate::init!(@struct $StructName { $($fields)* } = $default);
         impl $StructName {
-            pub fn default() -> Self {
-                $default
+            pub fn load() -> Self {
+                let state = $crate::sys::load_versioned::<$StructName>()
+                    .unwrap_or_else(|| $default);
+                std::println!("Loaded {:?}", state);
+                state
+            }
+            pub fn save(&self) -> bool {
+                if let Ok(_) = $crate::sys::save_versioned(self) {
+                    std::println!("Saved {:?}", self);
+                    return true;
+                }
+                return false;
             }
+        }
+    };
+    (struct $StructName:ident { $($fields:tt)* } = $default:expr) => {
+        $crate::init!(@struct $StructName { $($fields)* } = $default);
+        impl $StructName {
             pub fn load() -> Self {
                 let state = $crate::sys::load()
                     .and_then(|xs| $StructName::try_from_slice(&xs).map_err(|err| -1))
@@ -83,6 +145,22 @@ macro_rules! init {
             }
         }
     };
+    (@struct $StructName:ident { $($fields:tt)* } = $default:expr) => {
+        use $crate::prelude::{*, println};
+        use $crate::borsh::{self, *};
+        use $crate::structstruck::{self, *};
+        strike! {
+            #[strikethrough[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]]
+            struct $StructName {
+                $($fields)*
+            }
+        }
+        impl $StructName {
+            pub fn default() -> Self {
+                $default
+            }
+        }
+    };
 }
 
 #[macro_export]
@@ -95,6 +173,7 @@ macro_rules! go {
         #[allow(overflowing_literals, non_upper_case_globals)]
         pub unsafe extern "C" fn run() {
             use std::f32::consts::PI;
+            let _frame = $crate::sys::FrameGuard::begin();
             $($body)*
         }
         #[cfg(not(no_run))]
@@ -106,6 +185,7 @@ macro_rules! go {
         #[allow(overflowing_literals, non_upper_case_globals)]
         unsafe fn run() {
             use std::f32::consts::PI;
+            let _frame = $crate::sys::FrameGuard::begin();
             $($body)*
         }
     };
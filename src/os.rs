@@ -39,6 +39,220 @@ pub mod encoding {
     ) -> Result<Vec<u8>, base64::DecodeError> {
         b64_url_safe.decode(input)
     }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum DecodeHexError {
+        OddLength(usize),
+        InvalidCharacter { index: usize, character: char },
+    }
+    impl std::fmt::Display for DecodeHexError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::OddLength(len) => write!(f, "Odd hex string length {}", len),
+                Self::InvalidCharacter { index, character } => {
+                    write!(
+                        f,
+                        "Invalid hex character {:?} at index {}",
+                        character, index
+                    )
+                }
+            }
+        }
+    }
+    impl std::error::Error for DecodeHexError {}
+
+    /// Encodes bytes as a lowercase hex string
+    pub fn encode_hex<T: AsRef<[u8]>>(input: T) -> String {
+        input
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Encodes bytes as an uppercase hex string
+    pub fn encode_hex_upper<T: AsRef<[u8]>>(input: T) -> String {
+        input
+            .as_ref()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect()
+    }
+
+    /// Decodes a hex string. Accepts upper and lowercase digits.
+    pub fn decode_hex<T: AsRef<[u8]>>(input: T) -> Result<Vec<u8>, DecodeHexError> {
+        let input = input.as_ref();
+        if input.len() % 2 != 0 {
+            return Err(DecodeHexError::OddLength(input.len()));
+        }
+        let digit = |index: usize| {
+            let character = input[index] as char;
+            character
+                .to_digit(16)
+                .map(|n| n as u8)
+                .ok_or(DecodeHexError::InvalidCharacter { index, character })
+        };
+        (0..input.len())
+            .step_by(2)
+            .map(|i| Ok(digit(i)? << 4 | digit(i + 1)?))
+            .collect()
+    }
+
+    /// Serde `serialize_with` adapter for hex strings
+    pub fn as_hex<T: AsRef<[u8]>, S: Serializer>(v: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode_hex(v))
+    }
+
+    /// Serde `deserialize_with` adapter for hex strings
+    pub fn from_hex<'a, D: Deserializer<'a>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        use serde::de::Error;
+        String::deserialize(deserializer)
+            .and_then(|string| decode_hex(string).map_err(|err| Error::custom(err.to_string())))
+    }
+
+    // Lookup table for the reflected CRC-32 polynomial 0xEDB88320
+    const CRC32_TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut j = 0;
+            while j < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xedb88320
+                } else {
+                    crc >> 1
+                };
+                j += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    };
+
+    /// CRC-32 (IEEE 802.3, as used by zlib/PNG): reflected polynomial
+    /// 0xEDB88320, initial value 0xFFFFFFFF, final xor 0xFFFFFFFF.
+    pub fn crc32<T: AsRef<[u8]>>(input: T) -> u32 {
+        !input.as_ref().iter().fold(!0u32, |crc, b| {
+            CRC32_TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+        })
+    }
+
+    /// Checksummed byte blobs with a trailing little-endian `crc32`
+    pub mod checked {
+        use super::crc32;
+
+        /// Appends the CRC-32 of `data` as 4 little-endian bytes
+        pub fn wrap<T: AsRef<[u8]>>(data: T) -> Vec<u8> {
+            let data = data.as_ref();
+            [data, &crc32(data).to_le_bytes()].concat()
+        }
+
+        /// Verifies and strips the trailing CRC-32. Returns `None` if the
+        /// data is too short or the checksum doesn't match.
+        pub fn unwrap<T: AsRef<[u8]>>(data: T) -> Option<Vec<u8>> {
+            let data = data.as_ref();
+            if data.len() < 4 {
+                return None;
+            }
+            let (bytes, checksum) = data.split_at(data.len() - 4);
+            let checksum = u32::from_le_bytes(checksum.try_into().ok()?);
+            if crc32(bytes) != checksum {
+                return None;
+            }
+            Some(bytes.to_vec())
+        }
+    }
+}
+
+/// Request/response over channels. Requests and replies are sent in tagged
+/// envelopes, so plain messages on the same channel pass through untouched.
+pub mod request {
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    pub type RequestId = u32;
+
+    const REQUEST_TAG: &[u8; 4] = b"\0req";
+    const REPLY_TAG: &[u8; 4] = b"\0rep";
+
+    fn wrap(tag: &[u8; 4], id: RequestId, data: &[u8]) -> Vec<u8> {
+        [&tag[..], &id.to_le_bytes(), data].concat()
+    }
+
+    fn parse<'a>(tag: &[u8; 4], msg: &'a [u8]) -> Option<(RequestId, &'a [u8])> {
+        let rest = msg.strip_prefix(&tag[..])?;
+        let id = rest.get(..4)?.try_into().ok()?;
+        Some((RequestId::from_le_bytes(id), &rest[4..]))
+    }
+
+    pub fn wrap_request(id: RequestId, data: &[u8]) -> Vec<u8> {
+        wrap(REQUEST_TAG, id, data)
+    }
+
+    pub fn wrap_reply(id: RequestId, data: &[u8]) -> Vec<u8> {
+        wrap(REPLY_TAG, id, data)
+    }
+
+    /// Reads a request envelope, or `None` for any other message
+    pub fn parse_request(msg: &[u8]) -> Option<(RequestId, &[u8])> {
+        parse(REQUEST_TAG, msg)
+    }
+
+    /// Reads a reply envelope, or `None` for any other message
+    pub fn parse_reply(msg: &[u8]) -> Option<(RequestId, &[u8])> {
+        parse(REPLY_TAG, msg)
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Incoming<'a> {
+        /// A reply to a request that was waiting on one
+        Reply(RequestId, &'a [u8]),
+        /// A message that isn't a reply
+        Message(&'a [u8]),
+        /// A reply nobody is waiting on, e.g. a duplicate or one for a request
+        /// made before a reload
+        Dropped(RequestId),
+    }
+
+    /// Tracks requests that are waiting on a reply. Serializable, so it can live
+    /// in game state alongside the connection.
+    #[derive(Debug, Default, Clone, PartialEq, BorshSerialize, BorshDeserialize)]
+    pub struct Pending {
+        next_id: RequestId,
+        waiting: Vec<RequestId>,
+    }
+
+    impl Pending {
+        /// Allocates an id for a new request and starts waiting on it
+        pub fn start(&mut self) -> RequestId {
+            let id = self.next_id;
+            self.next_id = self.next_id.wrapping_add(1);
+            self.waiting.push(id);
+            id
+        }
+
+        /// Stops waiting on a request, e.g. after a timeout
+        pub fn cancel(&mut self, id: RequestId) {
+            self.waiting.retain(|&waiting| waiting != id);
+        }
+
+        pub fn is_waiting(&self, id: RequestId) -> bool {
+            self.waiting.contains(&id)
+        }
+
+        /// Sorts a received message, matching replies to waiting requests
+        pub fn receive<'a>(&mut self, msg: &'a [u8]) -> Incoming<'a> {
+            let Some((id, data)) = parse_reply(msg) else {
+                return Incoming::Message(msg);
+            };
+            if !self.is_waiting(id) {
+                return Incoming::Dropped(id);
+            }
+            self.cancel(id);
+            Incoming::Reply(id, data)
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -116,8 +330,59 @@ pub struct ProgramFile {
     pub version: u32,
 }
 
+impl QueryResult<ProgramFile> {
+    /// Decodes the file contents without consuming the query result.
+    /// Returns `None` while loading, on error, or if the contents fail to decode.
+    pub fn parsed<T: borsh::BorshDeserialize>(&self) -> Option<T> {
+        let file = self.data.as_ref()?;
+        T::try_from_slice(&file.contents).ok()
+    }
+
+    /// Shows a pending local change in place of the file contents, so the UI can
+    /// update before the command that makes it round-trips. Once the watched file's
+    /// version passes `base_version`, the server has caught up or moved on, and
+    /// `local` is cleared. Set `local` to `None` to roll back a rejected change.
+    pub fn with_optimistic<T: borsh::BorshSerialize>(
+        mut self,
+        local: &mut Option<Optimistic<T>>,
+    ) -> Self {
+        let Some(pending) = local.as_ref() else {
+            return self;
+        };
+        if let Some(file) = &self.data {
+            if file.version > pending.base_version {
+                *local = None;
+                return self;
+            }
+        }
+        let Ok(contents) = borsh::to_vec(&pending.value) else {
+            return self;
+        };
+        let file = self.data.get_or_insert_with(|| ProgramFile {
+            checksum: String::new(),
+            contents: vec![],
+            created_at: 0,
+            updated_at: 0,
+            prev_txn_hash: None,
+            txn_hash: String::new(),
+            version: pending.base_version,
+        });
+        file.contents = contents;
+        self
+    }
+}
+
+/// A locally predicted file value, see `QueryResult::with_optimistic`
+#[derive(Debug, Clone, PartialEq, borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct Optimistic<T> {
+    pub value: T,
+    /// The file version the change was made against. Use 0 for a new file.
+    pub base_version: u32,
+}
+
 pub mod client {
     use borsh::{BorshDeserialize, BorshSerialize};
+    use std::collections::BTreeMap;
 
     use super::*;
 
@@ -231,6 +496,174 @@ pub mod client {
                     }),
                 }
             }
+
+            /// Subscribes to a channel that reconnects on its own after dropping.
+            pub fn subscribe_with_reconnect(
+                program_id: &str,
+                channel_kind: &str,
+                channel_id: &str,
+                policy: ReconnectPolicy,
+            ) -> ReconnectingChannel {
+                ReconnectingChannel {
+                    program_id: program_id.to_string(),
+                    channel_kind: channel_kind.to_string(),
+                    channel_id: channel_id.to_string(),
+                    policy,
+                    attempt: 0,
+                    next_attempt_tick: 0,
+                    started: false,
+                    connected: false,
+                    closed: false,
+                }
+            }
+        }
+
+        /// Backoff between reconnection attempts, measured in ticks.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct ReconnectPolicy {
+            pub initial_delay: u32,
+            pub max_delay: u32,
+            // Gives up after this many failed retries. The first connect isn't a retry.
+            pub max_attempts: Option<u32>,
+        }
+        impl Default for ReconnectPolicy {
+            fn default() -> Self {
+                Self {
+                    initial_delay: 30,
+                    max_delay: 600,
+                    max_attempts: None,
+                }
+            }
+        }
+
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum ConnectionState {
+            Connected,
+            Reconnecting { attempt: u32 },
+            Closed,
+        }
+
+        /// A channel subscription that re-establishes itself with backoff.
+        ///
+        /// Messages are not buffered while disconnected: anything the server sends
+        /// during the gap is lost, and sends should wait for a connection.
+        #[derive(Debug, Clone)]
+        pub struct ReconnectingChannel {
+            program_id: String,
+            channel_kind: String,
+            channel_id: String,
+            policy: ReconnectPolicy,
+            attempt: u32,
+            next_attempt_tick: usize,
+            started: bool,
+            connected: bool,
+            closed: bool,
+        }
+        impl ReconnectingChannel {
+            /// Returns the open connection, attempting to reconnect when it's due.
+            /// Call this every frame.
+            pub fn connection(&mut self) -> Option<Connection<Open>> {
+                if self.closed {
+                    return None;
+                }
+                match Channel::subscribe(&self.program_id, &self.channel_kind, &self.channel_id) {
+                    Channel::Connected(conn) => {
+                        self.poll(true, crate::sys::tick());
+                        Some(conn)
+                    }
+                    Channel::Disconnected(conn) => {
+                        if self.poll(false, crate::sys::tick()) {
+                            conn.connect();
+                        }
+                        None
+                    }
+                }
+            }
+
+            // Updates the state for this tick. Returns true if a connect attempt is due.
+            pub(crate) fn poll(&mut self, connected: bool, tick: usize) -> bool {
+                if self.closed {
+                    return false;
+                }
+                self.connected = connected;
+                if connected {
+                    self.attempt = 0;
+                    self.started = true;
+                    return false;
+                }
+                if tick < self.next_attempt_tick {
+                    return false;
+                }
+                // The last retry had its full delay to connect and didn't
+                let exhausted = self
+                    .policy
+                    .max_attempts
+                    .is_some_and(|max| self.attempt >= max);
+                if self.started && exhausted {
+                    self.closed = true;
+                    return false;
+                }
+                if self.started {
+                    self.attempt += 1;
+                }
+                self.started = true;
+                // Double the delay after each failed retry
+                let delay = (self.policy.initial_delay as u64)
+                    .saturating_mul(1 << self.attempt.min(16))
+                    .min(self.policy.max_delay as u64);
+                self.next_attempt_tick = tick + delay as usize;
+                true
+            }
+
+            pub fn connection_state(&self) -> ConnectionState {
+                if self.closed {
+                    ConnectionState::Closed
+                } else if self.connected {
+                    ConnectionState::Connected
+                } else {
+                    ConnectionState::Reconnecting {
+                        attempt: self.attempt,
+                    }
+                }
+            }
+
+            /// Closes the connection and stops any further reconnection attempts.
+            pub fn close(&mut self) {
+                if !self.closed {
+                    close(&self.program_id, &self.channel_kind, &self.channel_id);
+                }
+                self.closed = true;
+                self.connected = false;
+            }
+        }
+
+        #[cfg(not(feature = "unstable-host"))]
+        fn close(_program_id: &str, _channel_kind: &str, _channel_id: &str) {}
+
+        #[cfg(feature = "unstable-host")]
+        fn close(program_id: &str, channel_kind: &str, channel_id: &str) {
+            #[link(wasm_import_module = "@turbo_genesis/turbo_os")]
+            extern "C" {
+                #[link_name = "channel_close"]
+                fn turbo_genesis_channel_close(
+                    program_id_ptr: *const u8,
+                    program_id_len: u32,
+                    channel_kind_ptr: *const u8,
+                    channel_kind_len: u32,
+                    channel_id_ptr: *const u8,
+                    channel_id_len: u32,
+                ) -> u32;
+            }
+            unsafe {
+                turbo_genesis_channel_close(
+                    program_id.as_ptr(),
+                    program_id.len() as u32,
+                    channel_kind.as_ptr(),
+                    channel_kind.len() as u32,
+                    channel_id.as_ptr(),
+                    channel_id.len() as u32,
+                );
+            }
         }
 
         #[derive(Debug)]
@@ -275,6 +708,60 @@ pub mod client {
 
             /// Sends a message to the channel.
             pub fn send(&self, data: &[u8]) -> Result<(), std::io::Error> {
+                match self.send_raw(data) {
+                    (0, _) => Ok(()), // STATUS_OK
+                    // Parse err into error string
+                    (_, Some(msg)) => {
+                        Err(std::io::Error::new(std::io::ErrorKind::NotConnected, msg))
+                    }
+                    // Default error
+                    (_, None) => Err(std::io::Error::new(
+                        std::io::ErrorKind::NotConnected,
+                        "Failed to send data",
+                    )),
+                }
+            }
+
+            /// Sends a message without waiting on a congested channel.
+            pub fn try_send(&self, data: &[u8]) -> Result<(), SendError> {
+                const STATUS_PENDING: u32 = 1;
+                match self.send_raw(data) {
+                    (0, _) => Ok(()),
+                    (STATUS_PENDING, _) => Err(SendError::WouldBlock),
+                    _ if !self.is_connected() => Err(SendError::Disconnected),
+                    (_, msg) => Err(SendError::Failed(
+                        msg.unwrap_or_else(|| "Failed to send data".to_string()),
+                    )),
+                }
+            }
+
+            /// Sends a message as a request. Pass received messages through
+            /// `Pending::receive` to pick out the reply.
+            pub fn request(
+                &self,
+                pending: &mut request::Pending,
+                data: &[u8],
+            ) -> Result<request::RequestId, std::io::Error> {
+                let id = pending.start();
+                let sent = self.send(&request::wrap_request(id, data));
+                if sent.is_err() {
+                    pending.cancel(id);
+                }
+                sent.map(|_| id)
+            }
+
+            /// Serializes and sends a message without waiting on a congested channel.
+            pub fn try_send_as<T: BorshSerialize>(&self, msg: &T) -> Result<(), SendError> {
+                let data = msg.try_to_vec().map_err(SendError::Serialize)?;
+                self.try_send(&data)
+            }
+
+            /// Checks if the channel is still connected.
+            pub fn is_connected(&self) -> bool {
+                is_connected(&self.program_id, &self.channel_kind, &self.channel_id)
+            }
+
+            fn send_raw(&self, data: &[u8]) -> (u32, Option<String>) {
                 let err = &mut [0; 1024];
                 let mut err_len = 0;
                 let status = unsafe {
@@ -291,27 +778,31 @@ pub mod client {
                         &mut err_len,
                     )
                 };
+                let err = (err_len > 0)
+                    .then(|| String::from_utf8_lossy(&err[..err_len as usize]).to_string());
+                (status, err)
+            }
+        }
 
-                match status {
-                    0 => Ok(()), // STATUS_OK
-                    _ => {
-                        // Parse err into error string
-                        if err_len > 0 {
-                            let bytes = err[..err_len as usize].to_vec();
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::NotConnected,
-                                String::from_utf8_lossy(&bytes).to_string(),
-                            ));
-                        }
-                        // Default error
-                        Err(std::io::Error::new(
-                            std::io::ErrorKind::NotConnected,
-                            "Failed to send data",
-                        ))
-                    }
+        #[derive(Debug)]
+        pub enum SendError {
+            // The channel is connected but can't take more messages this frame
+            WouldBlock,
+            Disconnected,
+            Serialize(std::io::Error),
+            Failed(String),
+        }
+        impl std::fmt::Display for SendError {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    Self::WouldBlock => write!(f, "Channel is congested"),
+                    Self::Disconnected => write!(f, "Channel is disconnected"),
+                    Self::Serialize(err) => write!(f, "Failed to serialize message: {err}"),
+                    Self::Failed(msg) => write!(f, "Failed to send data: {msg}"),
                 }
             }
         }
+        impl std::error::Error for SendError {}
 
         impl Connection<Closed> {
             /// Attempts to reconnect to the channel.
@@ -457,6 +948,249 @@ pub mod client {
         res
     }
 
+    /// Watches multiple event types at once, returning the newest matching event.
+    /// An empty `event_types` slice watches all types (same as `watch_events(_, None)`).
+    /// When `since` is set, only events with `created_at` strictly greater than it are returned.
+    pub fn watch_events_with_opts(
+        program_id: &str,
+        event_types: &[&str],
+        since: Option<u32>,
+    ) -> QueryResult<ProgramEvent> {
+        // Each event type is its own subscription on the host
+        let results = if event_types.is_empty() {
+            vec![watch_events(program_id, None)]
+        } else {
+            event_types
+                .iter()
+                .map(|event_type| watch_events(program_id, Some(event_type)))
+                .collect::<Vec<_>>()
+        };
+
+        // Pick the newest event created after the since timestamp
+        let data = results
+            .iter()
+            .filter_map(|res| res.data.as_ref())
+            .filter(|event| since.map_or(true, |since| event.created_at > since))
+            .max_by_key(|event| event.created_at)
+            .cloned();
+
+        // Surface loading and errors only when there's nothing to show
+        let loading = data.is_none() && results.iter().any(|res| res.loading);
+        let error = match data {
+            Some(_) => None,
+            None => results.iter().find_map(|res| res.error.clone()),
+        };
+
+        QueryResult {
+            loading,
+            data,
+            error,
+        }
+    }
+
+    pub fn watch_events_as<T: BorshDeserialize>(
+        program_id: &str,
+        event_type: Option<&str>,
+    ) -> QueryResult<(ProgramEvent, T)> {
+        let QueryResult {
+            loading,
+            data,
+            error,
+        } = watch_events(program_id, event_type);
+
+        // Pass through loading states and errors without decoding
+        let event = match data {
+            Some(event) if !loading => event,
+            _ => {
+                return QueryResult {
+                    loading,
+                    data: None,
+                    error,
+                }
+            }
+        };
+
+        // Some events carry no payload
+        if event.data.is_empty() {
+            return QueryResult {
+                loading,
+                data: None,
+                error: Some(format!("Event {} has no data to decode", event.id)),
+            };
+        }
+
+        // Decode event data
+        match T::try_from_slice(&event.data) {
+            Ok(value) => QueryResult {
+                loading,
+                data: Some((event, value)),
+                error,
+            },
+            Err(err) => QueryResult {
+                loading,
+                data: None,
+                error: Some(err.to_string()),
+            },
+        }
+    }
+
+    // Recent events seen for each program id and event type, newest first
+    static mut EVENT_FEEDS: BTreeMap<(String, String), Vec<ProgramEvent>> = BTreeMap::new();
+
+    /// Collects the events reported by `watch_events` into a feed, newest first, keeping
+    /// at most `limit`. The host only reports the latest event, so the feed holds the
+    /// events seen since the first call. Call it every frame to avoid missing any.
+    /// The feed is `Some(vec![])` until an event arrives.
+    pub fn watch_events_list(
+        program_id: &str,
+        event_type: Option<&str>,
+        limit: usize,
+    ) -> QueryResult<Vec<ProgramEvent>> {
+        let QueryResult {
+            loading,
+            data,
+            error,
+        } = watch_events(program_id, event_type);
+        let feeds = unsafe { &mut *std::ptr::addr_of_mut!(EVENT_FEEDS) };
+        let key = (program_id.to_string(), event_type.unwrap_or("").to_string());
+        let feed = feeds.entry(key).or_default();
+        push_event(feed, data, limit);
+        QueryResult {
+            loading,
+            data: Some(feed.clone()),
+            error,
+        }
+    }
+
+    /// Same as `watch_events_list`, with each event's data decoded. Events that fail
+    /// to decode are left out and the first failure is reported as the error.
+    pub fn watch_events_list_as<T: BorshDeserialize>(
+        program_id: &str,
+        event_type: Option<&str>,
+        limit: usize,
+    ) -> QueryResult<Vec<(ProgramEvent, T)>> {
+        let QueryResult {
+            loading,
+            data,
+            mut error,
+        } = watch_events_list(program_id, event_type, limit);
+        let mut decoded = vec![];
+        for event in data.unwrap_or_default() {
+            match T::try_from_slice(&event.data) {
+                Ok(value) => decoded.push((event, value)),
+                Err(err) => {
+                    error.get_or_insert_with(|| format!("Event {}: {}", event.id, err));
+                }
+            }
+        }
+        QueryResult {
+            loading,
+            data: Some(decoded),
+            error,
+        }
+    }
+
+    // Adds an event to a newest-first feed, skipping ones already in it
+    pub(crate) fn push_event(
+        feed: &mut Vec<ProgramEvent>,
+        event: Option<ProgramEvent>,
+        limit: usize,
+    ) {
+        if let Some(event) = event {
+            if !feed.iter().any(|e| e.id == event.id) {
+                let i = feed.partition_point(|e| e.created_at > event.created_at);
+                feed.insert(i, event);
+            }
+        }
+        feed.truncate(limit);
+    }
+
+    /// Retries a query that failed, waiting a number of ticks that doubles after
+    /// each failure. Keep it in game state and `poll` it every frame in place of
+    /// calling the query directly.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct QueryRetry {
+        max_attempts: u32,
+        backoff: u32,
+        attempts: u32,
+        next_tick: usize,
+        error: Option<String>,
+    }
+
+    impl QueryRetry {
+        /// Allows up to `max_attempts` failures, starting with a `backoff` tick wait
+        pub fn new(max_attempts: u32, backoff: u32) -> Self {
+            Self {
+                max_attempts,
+                backoff,
+                attempts: 0,
+                next_tick: 0,
+                error: None,
+            }
+        }
+
+        /// Failed attempts since the last success
+        pub fn attempts(&self) -> u32 {
+            self.attempts
+        }
+
+        pub fn is_exhausted(&self) -> bool {
+            self.attempts >= self.max_attempts
+        }
+
+        /// Clears the failures so the next `poll` runs the query right away, e.g.
+        /// when the player chooses to retry after running out of attempts.
+        pub fn reset(&mut self) {
+            self.attempts = 0;
+            self.next_tick = 0;
+            self.error = None;
+        }
+
+        /// Runs the query unless it's waiting to retry. Errors count as failed
+        /// attempts, but loading doesn't, and a success resets the count. While
+        /// waiting, the result is loading. Once out of attempts, the last error is
+        /// returned without running the query again until `reset` is called.
+        pub fn poll<T>(&mut self, query: impl FnOnce() -> QueryResult<T>) -> QueryResult<T> {
+            self.poll_at(crate::sys::tick(), query)
+        }
+
+        pub(crate) fn poll_at<T>(
+            &mut self,
+            tick: usize,
+            query: impl FnOnce() -> QueryResult<T>,
+        ) -> QueryResult<T> {
+            if self.is_exhausted() {
+                return QueryResult {
+                    loading: false,
+                    data: None,
+                    error: self.error.clone(),
+                };
+            }
+            if tick < self.next_tick {
+                return QueryResult {
+                    loading: true,
+                    data: None,
+                    error: None,
+                };
+            }
+            let res = query();
+            match &res.error {
+                Some(err) if !res.loading => {
+                    let delay = (self.backoff as u64).saturating_mul(1 << self.attempts.min(16));
+                    self.attempts += 1;
+                    self.next_tick = tick.saturating_add(delay as usize);
+                    self.error = Some(err.clone());
+                }
+                _ if res.data.is_some() => {
+                    self.attempts = 0;
+                    self.error = None;
+                }
+                _ => {}
+            }
+            res
+        }
+    }
+
     pub fn watch_file(program_id: &str, filepath: &str) -> QueryResult<ProgramFile> {
         watch_file_with_opts(program_id, filepath, &[("stream", "true")])
     }
@@ -528,6 +1262,142 @@ pub mod client {
         res
     }
 
+    pub mod fs {
+        use super::*;
+        use std::path::{Component, Path};
+
+        // Program that `fs` paths resolve against
+        static mut PROGRAM_ID: Option<String> = None;
+
+        /// Sets the program id used by `fs` queries. Call it once during init.
+        pub fn set_program_id(program_id: &str) {
+            unsafe { *std::ptr::addr_of_mut!(PROGRAM_ID) = Some(program_id.to_string()) }
+        }
+
+        pub fn program_id() -> Option<&'static str> {
+            unsafe { (*std::ptr::addr_of!(PROGRAM_ID)).as_deref() }
+        }
+
+        /// Lists the entry names under a directory of the current program.
+        /// A directory that doesn't exist resolves to an empty list with no error.
+        pub fn list(dir: impl AsRef<Path>) -> QueryResult<Vec<String>> {
+            let Some(program_id) = program_id() else {
+                return failed("ProgramIdNotSet");
+            };
+            let Some(dir) = dir_key(dir.as_ref()) else {
+                return failed("InvalidPath");
+            };
+            list_in(program_id, &dir)
+        }
+
+        // Joins path components with `/`. Returns `None` for paths leaving the
+        // program directory.
+        pub(crate) fn dir_key(dir: &Path) -> Option<String> {
+            let mut parts = vec![];
+            for component in dir.components() {
+                match component {
+                    Component::Normal(part) => parts.push(part.to_str()?),
+                    Component::CurDir | Component::RootDir => {}
+                    Component::ParentDir | Component::Prefix(_) => return None,
+                }
+            }
+            Some(parts.join("/"))
+        }
+
+        fn failed<T>(error: &str) -> QueryResult<T> {
+            QueryResult {
+                loading: false,
+                data: None,
+                error: Some(error.to_string()),
+            }
+        }
+
+        #[cfg(not(feature = "unstable-host"))]
+        fn list_in(_program_id: &str, _dir: &str) -> QueryResult<Vec<String>> {
+            failed("Unsupported")
+        }
+
+        #[cfg(feature = "unstable-host")]
+        fn list_in(program_id: &str, dir: &str) -> QueryResult<Vec<String>> {
+            #[link(wasm_import_module = "@turbo_genesis/turbo_os")]
+            extern "C" {
+                #[link_name = "list_files"]
+                fn turbo_genesis_list_files(
+                    program_id_ptr: *const u8,
+                    program_id_len: u32,
+                    dir_ptr: *const u8,
+                    dir_len: u32,
+                    out_data_ptr: *mut u8,
+                    out_data_cap: u32,
+                    out_data_len_ptr: *mut u32,
+                    out_err_ptr: *mut u8,
+                    out_err_len_ptr: *mut u32,
+                ) -> u32;
+            }
+            // const STATUS_COMPLETE: u32 = 0;
+            const STATUS_PENDING: u32 = 1;
+            const STATUS_FAILED: u32 = 2;
+            let data = &mut [0; 8192];
+            let mut data_len = 0;
+            let err = &mut [0; 1024];
+            let mut err_len = 0;
+            let status = unsafe {
+                turbo_genesis_list_files(
+                    program_id.as_ptr(),
+                    program_id.len() as u32,
+                    dir.as_ptr(),
+                    dir.len() as u32,
+                    data.as_mut_ptr(),
+                    data.len() as u32,
+                    &mut data_len,
+                    err.as_mut_ptr(),
+                    &mut err_len,
+                )
+            };
+            // Network error
+            if status == STATUS_FAILED {
+                return failed("NetworkError");
+            }
+
+            // Request is loading
+            if status == STATUS_PENDING {
+                return QueryResult {
+                    loading: true,
+                    data: None,
+                    error: None,
+                };
+            }
+
+            // Parse err into error string
+            if err_len > 0 {
+                let bytes = &err[..(err_len as usize).min(err.len())];
+                return failed(&String::from_utf8_lossy(bytes));
+            }
+
+            // The host reports the full length when the listing doesn't fit
+            if data_len as usize > data.len() {
+                return failed("ListingTooLarge");
+            }
+
+            // Request is complete. No data means the directory is empty or doesn't exist
+            if data_len == 0 {
+                return QueryResult {
+                    loading: false,
+                    data: Some(vec![]),
+                    error: None,
+                };
+            }
+            match serde_json::from_slice::<Vec<String>>(&data[..data_len as usize]) {
+                Ok(names) => QueryResult {
+                    loading: false,
+                    data: Some(names),
+                    error: None,
+                },
+                Err(err) => failed(&err.to_string()),
+            }
+        }
+    }
+
     #[deprecated(note = "please use `watch_file` instead")]
     pub fn read_file(program_id: &str, filepath: &str) -> Result<File, ReadError> {
         let query = "stream=true";
@@ -656,15 +1526,113 @@ pub mod client {
             .to_string()
     }
 
+    /// One command of a batch, with its data already serialized.
+    #[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+    pub struct BatchCommand {
+        pub name: String,
+        pub data: Vec<u8>,
+    }
+    impl BatchCommand {
+        pub fn new<T: BorshSerialize>(name: &str, data: &T) -> Result<Self, std::io::Error> {
+            Ok(Self {
+                name: name.to_string(),
+                data: data.try_to_vec()?,
+            })
+        }
+    }
+
+    /// Command data that knows its command name, so a batch can be built with
+    /// `vec![a.into_cmd()?, b.into_cmd()?]`.
+    pub trait IntoCommand: BorshSerialize + Sized {
+        const NAME: &'static str;
+
+        fn into_cmd(self) -> Result<BatchCommand, std::io::Error> {
+            BatchCommand::new(Self::NAME, &self)
+        }
+    }
+
+    /// Executes several commands in one transaction and returns its hash. Commands run
+    /// in the order given, and if any of them cancels, the whole batch is cancelled.
+    pub fn exec_batch(
+        program_id: &str,
+        commands: &[BatchCommand],
+    ) -> Result<String, std::io::Error> {
+        use std::io::{Error, ErrorKind};
+        let commands = commands.try_to_vec()?;
+        let tx_hash_url_safe_b64 = &mut [0; 43]; // url-safe, no-pad
+        let status = exec_batch_raw(program_id, &commands, tx_hash_url_safe_b64)?;
+        if status != 0 {
+            return Err(Error::other(format!(
+                "Batch was rejected with status {status}"
+            )));
+        }
+        std::str::from_utf8(tx_hash_url_safe_b64)
+            .map(|hash| hash.to_string())
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    #[cfg(not(feature = "unstable-host"))]
+    fn exec_batch_raw(_: &str, _: &[u8], _: &mut [u8; 43]) -> Result<u32, std::io::Error> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "exec_batch needs the unstable-host feature",
+        ))
+    }
+
+    #[cfg(feature = "unstable-host")]
+    fn exec_batch_raw(
+        program_id: &str,
+        commands: &[u8],
+        tx_hash: &mut [u8; 43],
+    ) -> Result<u32, std::io::Error> {
+        #[link(wasm_import_module = "@turbo_genesis/turbo_os")]
+        extern "C" {
+            #[link_name = "exec_batch"]
+            fn turbo_genesis_exec_batch(
+                program_id_ptr: *const u8,
+                program_id_len: u32,
+                commands_ptr: *const u8,
+                commands_len: u32,
+                tx_hash_ptr: *mut u8,
+            ) -> u32;
+        }
+        Ok(unsafe {
+            turbo_genesis_exec_batch(
+                program_id.as_ptr(),
+                program_id.len() as u32,
+                commands.as_ptr(),
+                commands.len() as u32,
+                tx_hash.as_mut_ptr(),
+            )
+        })
+    }
+
     pub fn user_id() -> Option<String> {
+        try_user_id().ok()
+    }
+
+    /// Fails with `PermissionDenied` when the player isn't signed in, and with
+    /// `InvalidData` if the ID doesn't fit its buffer or isn't valid UTF-8.
+    pub fn try_user_id() -> Result<String, std::io::Error> {
+        use std::io::{Error, ErrorKind};
         let data = &mut [0; 128];
         let mut data_len = 0;
         let ok = unsafe { turbo_genesis_get_user_id(data.as_mut_ptr(), &mut data_len) };
-        if ok == 0 {
-            String::from_utf8(data[..data_len as usize].to_vec()).ok()
-        } else {
-            None
+        if ok != 0 {
+            return Err(Error::new(ErrorKind::PermissionDenied, "Not authenticated"));
         }
+        // Never decode a partial ID
+        if data_len as usize > data.len() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "User ID is {data_len} bytes but only {} were read",
+                    data.len()
+                ),
+            ));
+        }
+        String::from_utf8(data[..data_len as usize].to_vec())
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
     }
 }
 
@@ -842,6 +1810,24 @@ pub mod server {
         unsafe { turbo_os_secs_since_unix_epoch() }
     }
 
+    // Time and randomness from the server host, for use in command handlers in
+    // place of the client's `sys::time`/`sys::random`.
+
+    /// Unix timestamp in whole seconds, from `secs_since_unix_epoch`.
+    pub fn time() -> u32 {
+        secs_since_unix_epoch()
+    }
+
+    /// Random value filled from the host's random bytes.
+    pub fn random() -> u64 {
+        random_number::<u64>()
+    }
+
+    /// Seeded rng for drawing several random values within one command.
+    pub fn rng() -> crate::sys::random::Rng {
+        crate::sys::random::Rng::new(random())
+    }
+
     pub fn get_user_id() -> String {
         let mut user_id = vec![0; unsafe { turbo_os_get_user_id_len() }];
         unsafe { turbo_os_get_user_id(user_id.as_mut_ptr()) };
@@ -869,6 +1855,38 @@ pub mod server {
         unsafe { turbo_os_log(message.as_ptr(), message.len()) };
     }
 
+    // Whether `finish` logs each command's outcome
+    static mut LOG_OUTCOMES: bool = true;
+
+    /// Turns outcome logging in `finish` on or off, e.g. to quiet production logs.
+    pub fn set_log_outcomes(enabled: bool) {
+        unsafe { LOG_OUTCOMES = enabled }
+    }
+
+    /// Ends a command handler, returning `COMMIT` for `Ok` and `CANCEL` for `Err`.
+    /// Also logs a JSON record of the command, user, outcome, and error message.
+    pub fn finish<E: std::fmt::Display>(command: &str, result: Result<(), E>) -> usize {
+        if unsafe { LOG_OUTCOMES } {
+            let error = result.as_ref().err().map(|err| err.to_string());
+            log(&outcome_record(command, &get_user_id(), error.as_deref()));
+        }
+        match result {
+            Ok(()) => COMMIT,
+            Err(_) => CANCEL,
+        }
+    }
+
+    pub(crate) fn outcome_record(command: &str, user_id: &str, error: Option<&str>) -> String {
+        let outcome = if error.is_some() { "cancel" } else { "commit" };
+        serde_json::json!({
+            "command": command,
+            "user_id": user_id,
+            "outcome": outcome,
+            "error": error,
+        })
+        .to_string()
+    }
+
     pub fn emit(event_type: &str, data: &[u8]) {
         unsafe {
             turbo_os_emit_event(
@@ -880,6 +1898,16 @@ pub mod server {
         };
     }
 
+    /// Serializes `value` with borsh and emits it. Nothing is emitted if serialization fails.
+    pub fn emit_typed<T: BorshSerialize>(
+        event_type: &str,
+        value: &T,
+    ) -> Result<(), std::io::Error> {
+        let data = value.try_to_vec()?;
+        emit(event_type, &data);
+        Ok(())
+    }
+
     #[deprecated]
     pub fn read_file_(filepath: &str) -> Result<Vec<u8>, &'static str> {
         let mut data = vec![0; 8192];
@@ -910,10 +1938,35 @@ pub mod server {
                 &mut data_len,
             )
         };
-        if err != 0 {
-            return Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+        read_file_result(err, data, data_len)
+    }
+
+    // The host reports a missing file with this status. Other non-zero statuses are
+    // failures, which mustn't be mistaken for a missing file.
+    const READ_FILE_NOT_FOUND: usize = 1;
+
+    pub(crate) fn read_file_result(
+        status: usize,
+        mut data: Vec<u8>,
+        data_len: usize,
+    ) -> Result<Vec<u8>, std::io::Error> {
+        match status {
+            0 if data_len > data.len() => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "file is {data_len} bytes, more than the {} byte buffer",
+                    data.len()
+                ),
+            )),
+            0 => {
+                data.truncate(data_len);
+                Ok(data)
+            }
+            READ_FILE_NOT_FOUND => Err(std::io::Error::from(std::io::ErrorKind::NotFound)),
+            code => Err(std::io::Error::other(format!(
+                "read_file failed with status {code}"
+            ))),
         }
-        Ok(data[..data_len].to_vec())
     }
 
     #[deprecated]
@@ -938,6 +1991,98 @@ pub mod server {
         return Ok(data.len());
     }
 
+    pub mod fs {
+        use super::*;
+        use std::io;
+
+        /// Writes a borsh-encoded value to a file in the program's directory.
+        pub fn write<T: BorshSerialize>(path: &str, value: &T) -> Result<(), io::Error> {
+            check_path(path)?;
+            write_file(path, &value.try_to_vec()?)?;
+            Ok(())
+        }
+
+        /// Reads a borsh-encoded value. Returns `None` if the file doesn't exist, and
+        /// an error for any other failure.
+        pub fn read<T: BorshDeserialize>(path: &str) -> Result<Option<T>, io::Error> {
+            check_path(path)?;
+            match read_file(path) {
+                Ok(bytes) => Ok(Some(T::try_from_slice(&bytes)?)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+
+        /// Reads, updates, and writes back a value. If the read fails, `f` isn't
+        /// called and nothing is written. This assumes the host runs each command
+        /// as a single transaction, so no other command writes the file between the
+        /// read and the write. The SDK can't enforce that itself.
+        pub fn update<T: BorshSerialize + BorshDeserialize>(
+            path: &str,
+            f: impl FnOnce(Option<T>) -> T,
+        ) -> Result<T, io::Error> {
+            let value = f(read(path)?);
+            write(path, &value)?;
+            Ok(value)
+        }
+
+        // Rejects paths that would escape the program's directory
+        pub(crate) fn check_path(path: &str) -> Result<(), io::Error> {
+            let escapes = path.is_empty()
+                || path.starts_with('/')
+                || path.starts_with('\\')
+                || path.split(['/', '\\']).any(|part| part == "..");
+            if escapes {
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    format!("path {:?} is outside the program directory", path),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    /// Counts an action for a user and checks it against `max` per fixed window of
+    /// `window_secs`. Buckets are per user and per key. The count is kept with
+    /// `fs::update`, so it's only safe from concurrent commands if the host runs
+    /// each command as a single transaction. Returns false when the limit is
+    /// exceeded or the count can't be stored.
+    pub fn rate_limit(user_id: &str, key: &str, window_secs: u32, max: u32) -> bool {
+        // Hex keeps the path a single segment per part, whatever the ids contain
+        let path = format!(
+            "rate_limit/{}/{}",
+            encoding::encode_hex(user_id),
+            encoding::encode_hex(key)
+        );
+        let now = secs_since_unix_epoch();
+        let mut allowed = false;
+        let updated = fs::update(&path, |window: Option<(u32, u32)>| {
+            let (next, ok) = next_rate_window(window, now, window_secs, max);
+            allowed = ok;
+            next
+        });
+        updated.is_ok() && allowed
+    }
+
+    // Returns the updated window start and count, and whether the action fits.
+    // A rejected action isn't counted.
+    pub(crate) fn next_rate_window(
+        window: Option<(u32, u32)>,
+        now: u32,
+        window_secs: u32,
+        max: u32,
+    ) -> ((u32, u32), bool) {
+        match window {
+            Some((start, count)) if start <= now && now - start < window_secs && count >= max => {
+                ((start, count), false)
+            }
+            Some((start, count)) if start <= now && now - start < window_secs => {
+                ((start, count + 1), true)
+            }
+            _ => ((now, max.min(1)), max > 0),
+        }
+    }
+
     pub fn enqueue_command(
         program_id: &str,
         command: &str,
@@ -1032,11 +2177,128 @@ pub mod server {
         err == 0
     }
 
+    /// Replies to a request read with `request::parse_request`
+    pub fn channel_reply(user_id: &str, id: request::RequestId, data: &[u8]) -> bool {
+        channel_send(user_id, &request::wrap_reply(id, data))
+    }
+
     pub fn channel_broadcast(data: &[u8]) -> bool {
         let err = unsafe { turbo_os_channel_broadcast(data.as_ptr(), data.len()) };
         err == 0
     }
 
+    // Size of the host's channel message buffer
+    const MAX_CHANNEL_MESSAGE_SIZE: usize = 1024;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ChannelSettings {
+        pub interval_ms: Option<u32>,
+        pub max_message_size: usize,
+        pub idle_timeout_ms: Option<u32>,
+    }
+    impl Default for ChannelSettings {
+        fn default() -> Self {
+            Self {
+                interval_ms: None,
+                max_message_size: MAX_CHANNEL_MESSAGE_SIZE,
+                idle_timeout_ms: None,
+            }
+        }
+    }
+    impl ChannelSettings {
+        /// Calls `on_interval` when no message arrives for this long.
+        pub fn interval_ms(mut self, interval_ms: u32) -> Self {
+            self.interval_ms = Some(interval_ms);
+            self
+        }
+        /// Drops data messages larger than this. Capped at the host buffer size.
+        pub fn max_message_size(mut self, max_message_size: usize) -> Self {
+            self.max_message_size = max_message_size.min(MAX_CHANNEL_MESSAGE_SIZE);
+            self
+        }
+        /// Closes the channel after this long without any message. Measured by
+        /// adding up the receive waits that timed out since the last message.
+        pub fn idle_timeout(mut self, idle_timeout_ms: u32) -> Self {
+            self.idle_timeout_ms = Some(idle_timeout_ms);
+            self
+        }
+    }
+
+    pub trait ChannelHandler {
+        fn on_open(&mut self, _settings: &mut ChannelSettings) {}
+        fn on_connect(&mut self, _user_id: &str) {}
+        fn on_disconnect(&mut self, _user_id: &str) {}
+        // Same as on_connect and on_disconnect, but with the raw bytes the host sent
+        // with the event, e.g. a join token. The data is empty if there was none.
+        fn on_connect_with_data(&mut self, user_id: &str, _data: &[u8]) {
+            self.on_connect(user_id)
+        }
+        fn on_disconnect_with_data(&mut self, user_id: &str, _data: &[u8]) {
+            self.on_disconnect(user_id)
+        }
+        fn on_data(&mut self, _user_id: &str, _data: &[u8]) {}
+        fn on_interval(&mut self) {}
+        fn on_close(&mut self) {}
+    }
+
+    /// Runs a channel until it closes or goes idle for `idle_timeout_ms`.
+    pub fn channel_run<H: ChannelHandler>(handler: &mut H) {
+        let mut settings = ChannelSettings::default();
+        handler.on_open(&mut settings);
+        let mut idle_ms = 0;
+        while let Some(timeout_ms) = next_wait(&settings, idle_ms) {
+            match channel_recv_with_timeout(timeout_ms) {
+                Ok(message) => {
+                    idle_ms = 0;
+                    dispatch(handler, message, &settings);
+                }
+                Err(ChannelError::Timeout) => {
+                    idle_ms += timeout_ms as u64;
+                    // Idle timeouts that wake the loop early aren't intervals
+                    if settings.interval_ms == Some(timeout_ms) {
+                        handler.on_interval();
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        handler.on_close();
+    }
+
+    // How long to wait for the next message after `idle_ms` without one, or `None`
+    // once the idle timeout has been reached. The wait never runs past the timeout.
+    pub(crate) fn next_wait(settings: &ChannelSettings, idle_ms: u64) -> Option<u32> {
+        let remaining_ms = match settings.idle_timeout_ms {
+            Some(idle_timeout_ms) if idle_ms >= idle_timeout_ms as u64 => return None,
+            Some(idle_timeout_ms) => Some(idle_timeout_ms - idle_ms as u32),
+            None => None,
+        };
+        Some(match (settings.interval_ms, remaining_ms) {
+            (Some(interval_ms), Some(remaining_ms)) => interval_ms.min(remaining_ms),
+            (Some(interval_ms), None) => interval_ms,
+            (None, Some(remaining_ms)) => remaining_ms,
+            (None, None) => u32::MAX,
+        })
+    }
+
+    pub(crate) fn dispatch<H: ChannelHandler>(
+        handler: &mut H,
+        message: ChannelMessage,
+        settings: &ChannelSettings,
+    ) {
+        match message {
+            ChannelMessage::Connect(user_id, data) => handler.on_connect_with_data(&user_id, &data),
+            ChannelMessage::Disconnect(user_id, data) => {
+                handler.on_disconnect_with_data(&user_id, &data)
+            }
+            ChannelMessage::Data(user_id, data) => {
+                if data.len() <= settings.max_message_size {
+                    handler.on_data(&user_id, &data)
+                }
+            }
+        }
+    }
+
     pub fn random_number<T: Default + Copy>() -> T {
         let len = std::mem::size_of::<T>();
         let buf: &mut [u8; 32] = &mut [0u8; 32];
@@ -1149,3 +2411,346 @@ pub mod server {
     }
     pub use os_server_alert as alert;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnecting_channel_attempts() {
+        use client::channel::{Channel, ConnectionState, ReconnectPolicy};
+        let policy = ReconnectPolicy {
+            initial_delay: 10,
+            max_delay: 600,
+            max_attempts: Some(2),
+        };
+        let mut channel = Channel::subscribe_with_reconnect("p", "kind", "id", policy);
+        // The first connect isn't counted as a retry
+        assert!(channel.poll(false, 0));
+        assert_eq!(
+            channel.connection_state(),
+            ConnectionState::Reconnecting { attempt: 0 }
+        );
+        assert!(!channel.poll(false, 5));
+        channel.poll(true, 8);
+        assert_eq!(channel.connection_state(), ConnectionState::Connected);
+        // After a drop, retries back off until the last one has had its chance
+        assert!(channel.poll(false, 100));
+        assert_eq!(
+            channel.connection_state(),
+            ConnectionState::Reconnecting { attempt: 1 }
+        );
+        assert!(!channel.poll(false, 119));
+        assert!(channel.poll(false, 120));
+        assert_eq!(
+            channel.connection_state(),
+            ConnectionState::Reconnecting { attempt: 2 }
+        );
+        assert!(!channel.poll(false, 159));
+        assert_eq!(
+            channel.connection_state(),
+            ConnectionState::Reconnecting { attempt: 2 }
+        );
+        assert!(!channel.poll(false, 160));
+        assert_eq!(channel.connection_state(), ConnectionState::Closed);
+        // A closed channel stays closed
+        assert!(!channel.poll(true, 200));
+        assert_eq!(channel.connection_state(), ConnectionState::Closed);
+    }
+
+    #[test]
+    fn test_batch_commands_encode_in_order() {
+        use client::{BatchCommand, IntoCommand};
+        #[derive(borsh::BorshSerialize)]
+        struct Move(i32, i32);
+        impl IntoCommand for Move {
+            const NAME: &'static str = "move";
+        }
+        let batch = vec![
+            Move(1, 2).into_cmd().unwrap(),
+            BatchCommand::new("end_turn", &()).unwrap(),
+        ];
+        // Encoded like a list of (name, data) pairs
+        let pairs = vec![
+            ("move".to_string(), borsh::to_vec(&(1i32, 2i32)).unwrap()),
+            ("end_turn".to_string(), vec![]),
+        ];
+        assert_eq!(
+            borsh::to_vec(&batch).unwrap(),
+            borsh::to_vec(&pairs).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fs_dir_key() {
+        use client::fs::dir_key;
+        use std::path::Path;
+        assert_eq!(
+            dir_key(Path::new("saves/slot1/")),
+            Some("saves/slot1".into())
+        );
+        assert_eq!(dir_key(Path::new("./saves")), Some("saves".into()));
+        assert_eq!(dir_key(Path::new("")), Some("".into()));
+        assert_eq!(dir_key(Path::new("saves/../..")), None);
+    }
+
+    #[test]
+    fn test_with_optimistic() {
+        let file = |version: u32, value: u32| QueryResult {
+            loading: false,
+            data: Some(ProgramFile {
+                checksum: String::new(),
+                contents: borsh::to_vec(&value).unwrap(),
+                created_at: 0,
+                updated_at: 0,
+                prev_txn_hash: None,
+                txn_hash: String::new(),
+                version,
+            }),
+            error: None,
+        };
+        let mut local = Some(Optimistic {
+            value: 7u32,
+            base_version: 3,
+        });
+        assert_eq!(
+            file(3, 1).with_optimistic(&mut local).parsed::<u32>(),
+            Some(7)
+        );
+        assert!(local.is_some());
+        let loading = QueryResult::<ProgramFile> {
+            loading: true,
+            data: None,
+            error: None,
+        };
+        assert_eq!(loading.with_optimistic(&mut local).parsed::<u32>(), Some(7));
+        assert_eq!(
+            file(4, 8).with_optimistic(&mut local).parsed::<u32>(),
+            Some(8)
+        );
+        assert!(local.is_none());
+        assert_eq!(
+            file(3, 1).with_optimistic(&mut local).parsed::<u32>(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let bytes = [0x00, 0x0f, 0xab, 0xff];
+        assert_eq!(encoding::encode_hex(bytes), "000fabff");
+        assert_eq!(encoding::encode_hex_upper(bytes), "000FABFF");
+        assert_eq!(encoding::decode_hex("000fABff").unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_crc32_checked() {
+        assert_eq!(encoding::crc32("123456789"), 0xcbf43926);
+        let wrapped = encoding::checked::wrap([1, 2, 3]);
+        assert_eq!(encoding::checked::unwrap(&wrapped), Some(vec![1, 2, 3]));
+        assert_eq!(encoding::checked::unwrap(&wrapped[1..]), None);
+        assert_eq!(encoding::checked::unwrap([0, 0]), None);
+    }
+
+    #[test]
+    fn test_server_fs_path_sandbox() {
+        assert!(server::fs::check_path("users/alice").is_ok());
+        assert!(server::fs::check_path("a..b/c").is_ok());
+        for path in ["", "/etc/passwd", "../other", "users/../../x", "a\\..\\b"] {
+            let err = server::fs::check_path(path).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+        }
+    }
+
+    #[test]
+    fn test_server_read_file_result() {
+        use std::io::ErrorKind;
+        let read = |status, len| server::read_file_result(status, vec![7; 4], len);
+        assert_eq!(read(0, 2).unwrap(), [7, 7]);
+        assert_eq!(read(0, 5).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(read(1, 0).unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(read(3, 0).unwrap_err().kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_query_retry() {
+        let failed = || QueryResult::<u8> {
+            loading: false,
+            data: None,
+            error: Some("NetworkError".to_string()),
+        };
+        let loading = || QueryResult::<u8> {
+            loading: true,
+            data: None,
+            error: None,
+        };
+        let mut retry = client::QueryRetry::new(3, 10);
+        assert!(retry.poll_at(0, loading).loading);
+        assert_eq!(retry.attempts(), 0);
+        assert!(retry.poll_at(1, failed).error.is_some());
+        // Waits out the backoff without running the query
+        let res = retry.poll_at::<u8>(5, || unreachable!());
+        assert!(res.loading && res.error.is_none());
+        retry.poll_at(11, failed);
+        assert_eq!(retry.attempts(), 2);
+        // The second backoff is twice as long
+        assert!(retry.poll_at::<u8>(30, || unreachable!()).loading);
+        let res = retry.poll_at(31, || QueryResult {
+            loading: false,
+            data: Some(1u8),
+            error: None,
+        });
+        assert_eq!(res.data, Some(1));
+        assert_eq!(retry.attempts(), 0);
+        for tick in [40, 60, 100] {
+            retry.poll_at(tick, failed);
+        }
+        assert!(retry.is_exhausted());
+        let res = retry.poll_at::<u8>(1000, || unreachable!());
+        assert_eq!(res.error.as_deref(), Some("NetworkError"));
+        assert!(!res.loading);
+        retry.reset();
+        assert_eq!(retry.attempts(), 0);
+        assert!(retry.poll_at(1001, loading).loading);
+    }
+
+    #[test]
+    fn test_channel_idle_waits() {
+        use server::{next_wait, ChannelSettings};
+        let settings = ChannelSettings::default()
+            .interval_ms(400)
+            .idle_timeout(1000);
+        assert_eq!(next_wait(&settings, 0), Some(400));
+        assert_eq!(next_wait(&settings, 800), Some(200));
+        assert_eq!(next_wait(&settings, 1000), None);
+        let settings = ChannelSettings::default().idle_timeout(1000);
+        assert_eq!(next_wait(&settings, 250), Some(750));
+        assert_eq!(next_wait(&ChannelSettings::default(), 5000), Some(u32::MAX));
+    }
+
+    #[test]
+    fn test_channel_dispatch() {
+        use server::{dispatch, ChannelHandler, ChannelMessage, ChannelSettings};
+        #[derive(Default)]
+        struct Plain(Vec<String>);
+        impl ChannelHandler for Plain {
+            fn on_connect(&mut self, user_id: &str) {
+                self.0.push(format!("connect {user_id}"));
+            }
+            fn on_disconnect(&mut self, user_id: &str) {
+                self.0.push(format!("disconnect {user_id}"));
+            }
+        }
+        #[derive(Default)]
+        struct WithData(Vec<(String, Vec<u8>)>);
+        impl ChannelHandler for WithData {
+            fn on_connect_with_data(&mut self, user_id: &str, data: &[u8]) {
+                self.0.push((user_id.to_string(), data.to_vec()));
+            }
+        }
+        let settings = ChannelSettings::default();
+        let mut plain = Plain::default();
+        dispatch(
+            &mut plain,
+            ChannelMessage::Connect("a".into(), vec![1]),
+            &settings,
+        );
+        dispatch(
+            &mut plain,
+            ChannelMessage::Disconnect("a".into(), vec![]),
+            &settings,
+        );
+        assert_eq!(plain.0, ["connect a", "disconnect a"]);
+        let mut with_data = WithData::default();
+        dispatch(
+            &mut with_data,
+            ChannelMessage::Connect("b".into(), vec![7, 8]),
+            &settings,
+        );
+        assert_eq!(with_data.0, [("b".to_string(), vec![7, 8])]);
+    }
+
+    #[test]
+    fn test_request_replies() {
+        use request::*;
+        let mut pending = Pending::default();
+        let a = pending.start();
+        let b = pending.start();
+        assert_ne!(a, b);
+        let msg = wrap_request(a, b"ping");
+        assert_eq!(parse_request(&msg), Some((a, &b"ping"[..])));
+        assert_eq!(parse_reply(&msg), None);
+        let reply = wrap_reply(b, b"pong");
+        assert_eq!(pending.receive(&reply), Incoming::Reply(b, b"pong"));
+        // Late or duplicate replies are dropped
+        assert_eq!(pending.receive(&reply), Incoming::Dropped(b));
+        assert_eq!(pending.receive(b"hello"), Incoming::Message(b"hello"));
+        assert_eq!(pending.receive(b"\0rep"), Incoming::Message(b"\0rep"));
+        assert!(pending.is_waiting(a));
+    }
+
+    #[test]
+    fn test_outcome_record() {
+        let record = server::outcome_record("buy", "u1", Some("not enough gold"));
+        let value: serde_json::Value = serde_json::from_str(&record).unwrap();
+        assert_eq!(value["command"], "buy");
+        assert_eq!(value["user_id"], "u1");
+        assert_eq!(value["outcome"], "cancel");
+        assert_eq!(value["error"], "not enough gold");
+        let value: serde_json::Value =
+            serde_json::from_str(&server::outcome_record("buy", "u1", None)).unwrap();
+        assert_eq!(value["outcome"], "commit");
+        assert!(value["error"].is_null());
+    }
+
+    #[test]
+    fn test_rate_limit_windows() {
+        use server::next_rate_window;
+        let (window, ok) = next_rate_window(None, 100, 10, 2);
+        assert_eq!((window, ok), ((100, 1), true));
+        let (window, ok) = next_rate_window(Some(window), 105, 10, 2);
+        assert_eq!((window, ok), ((100, 2), true));
+        let (window, ok) = next_rate_window(Some(window), 109, 10, 2);
+        assert_eq!((window, ok), ((100, 2), false));
+        // A new window starts once the old one has passed
+        assert_eq!(next_rate_window(Some(window), 110, 10, 2), ((110, 1), true));
+        assert_eq!(next_rate_window(None, 100, 10, 0), ((100, 0), false));
+    }
+
+    #[test]
+    fn test_event_feed_is_newest_first() {
+        let event = |id: &str, created_at| ProgramEvent {
+            id: id.to_string(),
+            created_at,
+            program_id: String::new(),
+            tx_hash: String::new(),
+            kind: String::new(),
+            data: vec![],
+        };
+        let ids = |feed: &[ProgramEvent]| feed.iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+        let mut feed = vec![];
+        client::push_event(&mut feed, None, 2);
+        assert!(feed.is_empty());
+        client::push_event(&mut feed, Some(event("a", 1)), 2);
+        client::push_event(&mut feed, Some(event("a", 1)), 2);
+        client::push_event(&mut feed, Some(event("b", 2)), 2);
+        assert_eq!(ids(&feed), ["b", "a"]);
+        client::push_event(&mut feed, Some(event("c", 3)), 2);
+        assert_eq!(ids(&feed), ["c", "b"]);
+    }
+
+    #[test]
+    fn test_hex_decode_errors() {
+        assert_eq!(
+            encoding::decode_hex("abc"),
+            Err(encoding::DecodeHexError::OddLength(3))
+        );
+        assert_eq!(
+            encoding::decode_hex("0g"),
+            Err(encoding::DecodeHexError::InvalidCharacter {
+                index: 1,
+                character: 'g'
+            })
+        );
+    }
+}
@@ -1,11 +1,22 @@
 use crate::ffi;
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::cell::RefCell;
+use std::fmt::Write;
 
 pub fn tick() -> usize {
     ffi::sys::tick() as usize
 }
 
 pub fn rand() -> u32 {
-    ffi::sys::rand()
+    random::u32()
+}
+
+/// 64-bit FNV-1a hash of `bytes`. The output is stable across versions, so it
+/// is safe to persist or use as a seed.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
 pub fn log(text: &str) {
@@ -14,9 +25,79 @@ pub fn log(text: &str) {
     ffi::sys::log(ptr, len)
 }
 
+// Messages up to this length are formatted without allocating
+const LOG_BUFFER_CAPACITY: usize = 1024;
+
+thread_local! {
+    static LOG_BUFFER: RefCell<String> = RefCell::new(String::with_capacity(LOG_BUFFER_CAPACITY));
+}
+
+/// Logs formatted arguments, reusing a buffer instead of allocating a new string
+/// each call. Used by `log!` and `println!`.
+pub fn log_args(args: std::fmt::Arguments) {
+    with_formatted(args, log)
+}
+
+pub(crate) fn with_formatted(args: std::fmt::Arguments, f: impl FnOnce(&str)) {
+    if let Some(s) = args.as_str() {
+        return f(s);
+    }
+    LOG_BUFFER.with(|buffer| {
+        // The buffer is busy if a Display impl logs while being formatted
+        let Ok(mut buffer) = buffer.try_borrow_mut() else {
+            return f(&args.to_string());
+        };
+        buffer.clear();
+        if buffer.write_fmt(args).is_err() {
+            return;
+        }
+        f(&buffer);
+        // Don't hold on to the memory from an unusually long message
+        if buffer.capacity() > LOG_BUFFER_CAPACITY {
+            *buffer = String::with_capacity(LOG_BUFFER_CAPACITY);
+        }
+    })
+}
+
 #[macro_export]
 macro_rules! log {
-    ($fmt:expr $(, $($arg:tt)*)?) => { $crate::sys::log(&format!($fmt, $($($arg)*)?)) };
+    ($fmt:expr $(, $($arg:tt)*)?) => { $crate::sys::log_args(format_args!($fmt, $($($arg)*)?)) };
+}
+
+// Leveled logging. These are re-exported as `log::debug!`, `log::info!`, etc.
+// The message is only formatted when its level passes the console filter.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_at {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::sys::console::enabled($level) {
+            $crate::sys::console::log_args($level, format_args!($($arg)*))
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)*) => { $crate::log_at!($crate::sys::console::Level::Debug, $($arg)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)*) => { $crate::log_at!($crate::sys::console::Level::Info, $($arg)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)*) => { $crate::log_at!($crate::sys::console::Level::Warn, $($arg)*) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)*) => { $crate::log_at!($crate::sys::console::Level::Error, $($arg)*) };
 }
 
 /// @deprecated - use $crate::canvas::canvas_size
@@ -28,6 +109,14 @@ pub fn resolution() -> [u32; 2] {
 }
 
 pub fn save(data: &[u8]) -> Result<i32, i32> {
+    // Keep local storage entries alongside the game's bytes
+    let data = local::join_section(data, &local::encode());
+    let n = save_raw(&data)?;
+    local::mark_clean();
+    Ok(n)
+}
+
+fn save_raw(data: &[u8]) -> Result<i32, i32> {
     let ptr = data.as_ptr();
     let len = data.len() as u32;
     let n = ffi::sys::save(ptr, len);
@@ -40,6 +129,10 @@ pub fn save(data: &[u8]) -> Result<i32, i32> {
 }
 
 pub fn load() -> Result<&'static [u8], i32> {
+    load_raw().map(|data| local::split_section(data).0)
+}
+
+fn load_raw() -> Result<&'static [u8], i32> {
     unsafe {
         // Allocate a big buffer for reading/writing save data
         static mut TURBO_SAVE_DATA: [u8; 4096 * 1000] = [0; 4096 * 1000];
@@ -55,7 +148,198 @@ pub fn load() -> Result<&'static [u8], i32> {
     }
 }
 
+/// Opt-in versioning for saved state. Lets state survive struct changes
+/// across hot-reloads by migrating bytes saved under an older version.
+pub trait HotState: BorshSerialize + BorshDeserialize {
+    const VERSION: u32;
+
+    /// Converts bytes saved under an older `version`. Returning `None` falls
+    /// back to the default state.
+    fn migrate(_version: u32, _bytes: &[u8]) -> Option<Self> {
+        None
+    }
+}
+
+/// Saves state prefixed with its `HotState::VERSION`
+pub fn save_versioned<T: HotState>(state: &T) -> Result<i32, i32> {
+    let bytes = state.try_to_vec().map_err(|_| -1)?;
+    let data = [&T::VERSION.to_le_bytes()[..], &bytes].concat();
+    save(&data)
+}
+
+/// Loads state saved with `save_versioned`, migrating it if the version differs
+pub fn load_versioned<T: HotState>() -> Option<T> {
+    let data = load().ok()?;
+    if data.len() < 4 {
+        return None;
+    }
+    let (version, bytes) = data.split_at(4);
+    let version = u32::from_le_bytes(version.try_into().ok()?);
+    if version == T::VERSION {
+        T::try_from_slice(bytes).ok()
+    } else {
+        T::migrate(version, bytes)
+    }
+}
+
+/// Opened by `go!` around each frame. It records the first frame for input edge
+/// detection, and dropping it writes any local storage changes made during the frame.
+#[doc(hidden)]
+pub struct FrameGuard;
+impl FrameGuard {
+    pub fn begin() -> Self {
+        crate::input::record_frame(tick());
+        Self
+    }
+}
+impl Drop for FrameGuard {
+    fn drop(&mut self) {
+        local::flush();
+    }
+}
+
+/// Key-value storage that persists on this device. The host keeps a single
+/// save blob, so entries are stored in a section after the game's own save
+/// bytes and are written at most once per frame.
+pub mod local {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use std::collections::BTreeMap;
+
+    type Store = BTreeMap<String, Vec<u8>>;
+
+    // Ends a save blob that carries local entries:
+    // [game bytes][entries][entries len: u32 le][SECTION_MAGIC]
+    const SECTION_MAGIC: [u8; 8] = *b"turbo/lc";
+
+    // Lazily loaded from the save blob on first access
+    static mut LOCAL_STORE: Option<Store> = None;
+
+    // Set when entries changed since the save blob was last written
+    static mut DIRTY: bool = false;
+
+    fn store() -> &'static mut Store {
+        unsafe { (*std::ptr::addr_of_mut!(LOCAL_STORE)).get_or_insert_with(read_store) }
+    }
+
+    fn read_store() -> Store {
+        super::load_raw()
+            .ok()
+            .and_then(|data| split_section(data).1)
+            .and_then(|entries| Store::try_from_slice(entries).ok())
+            .unwrap_or_default()
+    }
+
+    fn mark_dirty() {
+        unsafe { *std::ptr::addr_of_mut!(DIRTY) = true }
+    }
+
+    pub(crate) fn mark_clean() {
+        unsafe { *std::ptr::addr_of_mut!(DIRTY) = false }
+    }
+
+    // Encodes the current entries. An empty store encodes to nothing so saves
+    // from games that never use local storage are unchanged.
+    pub(crate) fn encode() -> Vec<u8> {
+        match store().is_empty() {
+            true => vec![],
+            false => store().try_to_vec().unwrap_or_default(),
+        }
+    }
+
+    /// Splits a save blob into the game's bytes and the local entries, if any.
+    pub(crate) fn split_section(data: &[u8]) -> (&[u8], Option<&[u8]>) {
+        let Some(rest) = data.strip_suffix(&SECTION_MAGIC) else {
+            return (data, None);
+        };
+        let Some(len_at) = rest.len().checked_sub(4) else {
+            return (data, None);
+        };
+        let len = u32::from_le_bytes([
+            rest[len_at],
+            rest[len_at + 1],
+            rest[len_at + 2],
+            rest[len_at + 3],
+        ]);
+        match len_at.checked_sub(len as usize) {
+            Some(start) => (&rest[..start], Some(&rest[start..len_at])),
+            None => (data, None),
+        }
+    }
+
+    pub(crate) fn join_section(game: &[u8], entries: &[u8]) -> Vec<u8> {
+        if entries.is_empty() {
+            return game.to_vec();
+        }
+        let len = (entries.len() as u32).to_le_bytes();
+        [game, entries, &len, &SECTION_MAGIC].concat()
+    }
+
+    /// Writes changed entries to the save blob. `go!` calls this at the end of
+    /// every frame, and `sys::save` writes them along with the game's bytes.
+    pub fn flush() -> bool {
+        if !unsafe { *std::ptr::addr_of!(DIRTY) } {
+            return true;
+        }
+        let game = super::load().map(|data| data.to_vec()).unwrap_or_default();
+        let ok = super::save_raw(&join_section(&game, &encode())).is_ok();
+        if ok {
+            mark_clean();
+        }
+        ok
+    }
+
+    /// Stores a value under `key`. Returns false if it can't be encoded.
+    pub fn set<T: BorshSerialize>(key: &str, value: &T) -> bool {
+        match value.try_to_vec() {
+            Ok(bytes) => {
+                if store().get(key) != Some(&bytes) {
+                    store().insert(key.to_string(), bytes);
+                    mark_dirty();
+                }
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Returns `None` if the key is missing or its bytes no longer decode into `T`
+    pub fn get<T: BorshDeserialize>(key: &str) -> Option<T> {
+        store()
+            .get(key)
+            .and_then(|bytes| T::try_from_slice(bytes).ok())
+    }
+
+    pub fn remove(key: &str) {
+        if store().remove(key).is_some() {
+            mark_dirty();
+        }
+    }
+
+    pub fn clear() {
+        if !store().is_empty() {
+            store().clear();
+            mark_dirty();
+        }
+    }
+
+    /// Returns all keys in sorted order
+    pub fn keys() -> Vec<String> {
+        store().keys().cloned().collect()
+    }
+}
+
 pub mod time {
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    // Host ticks per second
+    static mut FRAME_RATE: u32 = 60;
+
+    // Caps the delta after a pause or a long stall
+    const MAX_DELTA_SECONDS: f32 = 0.1;
+
+    // Tick, timestamp, and delta of the last measured frame
+    static mut FRAME_TIME: Option<(usize, u64, f32)> = None;
+
     pub fn now() -> u64 {
         unsafe {
             #[link(wasm_import_module = "@turbo_genesis/sys")]
@@ -65,4 +349,692 @@ pub mod time {
             millis_since_unix_epoch()
         }
     }
+
+    /// Fires after a number of frames, once or repeatedly. Timers start counting
+    /// on their first `tick` and are serializable, so they can live in game state.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+    pub struct Timer {
+        frames: u64,
+        repeat: bool,
+        start: Option<u64>,
+        done: bool,
+    }
+    impl Timer {
+        /// Fires every `frames` frames. `every(0)` fires every frame.
+        pub fn every(frames: u32) -> Self {
+            Self {
+                frames: frames as u64,
+                repeat: true,
+                start: None,
+                done: false,
+            }
+        }
+
+        /// Fires once after `frames` frames.
+        pub fn once(frames: u32) -> Self {
+            Self {
+                repeat: false,
+                ..Self::every(frames)
+            }
+        }
+
+        /// Returns true on frames where the timer fires. Call once per frame.
+        pub fn tick(&mut self) -> bool {
+            self.tick_at(super::tick() as u64)
+        }
+
+        pub(crate) fn tick_at(&mut self, tick: u64) -> bool {
+            if self.done {
+                return false;
+            }
+            // Restart if the tick counter went backwards, e.g. after a reload
+            let start = match self.start {
+                Some(start) if start <= tick => start,
+                _ => *self.start.insert(tick),
+            };
+            if tick - start < self.frames {
+                return false;
+            }
+            if self.repeat {
+                self.start = Some(tick);
+            } else {
+                self.done = true;
+            }
+            true
+        }
+
+        /// Restarts the timer from the next `tick`.
+        pub fn reset(&mut self) {
+            self.start = None;
+            self.done = false;
+        }
+
+        /// Checks if a one-shot timer has fired.
+        pub fn is_done(&self) -> bool {
+            self.done
+        }
+    }
+
+    /// Host ticks per second used to convert ticks to time. Defaults to 60.
+    pub fn frame_rate() -> u32 {
+        unsafe { FRAME_RATE }
+    }
+
+    /// Sets the tick rate for hosts that don't run at 60fps.
+    pub fn set_frame_rate(fps: u32) {
+        unsafe { FRAME_RATE = fps.max(1) }
+    }
+
+    /// Wall-clock milliseconds since the Unix epoch
+    pub fn now_millis() -> u64 {
+        now()
+    }
+
+    /// Real seconds elapsed since the previous frame, clamped to 0.1s.
+    /// Returns the same value for every call within a frame.
+    pub fn delta_seconds() -> f32 {
+        let tick = super::tick();
+        unsafe {
+            let (delta, timestamp) = match FRAME_TIME {
+                Some((prev_tick, _, delta)) if prev_tick == tick => return delta,
+                Some((prev_tick, prev_timestamp, _)) if prev_tick < tick => {
+                    let timestamp = now();
+                    let frames = (tick - prev_tick) as f32;
+                    let elapsed = timestamp.saturating_sub(prev_timestamp) as f32 / 1000.0;
+                    ((elapsed / frames).min(MAX_DELTA_SECONDS), timestamp)
+                }
+                // Used on the first measured frame and after the tick counter resets
+                _ => (1.0 / frame_rate() as f32, now()),
+            };
+            FRAME_TIME = Some((tick, timestamp, delta));
+            delta
+        }
+    }
+}
+
+pub mod console {
+    /// Log levels, from most to least verbose
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Level {
+        Debug,
+        Info,
+        Warn,
+        Error,
+    }
+    impl Level {
+        pub fn prefix(self) -> &'static str {
+            match self {
+                Self::Debug => "[DEBUG]",
+                Self::Info => "[INFO]",
+                Self::Warn => "[WARN]",
+                Self::Error => "[ERROR]",
+            }
+        }
+    }
+
+    // Messages below this level are dropped
+    static mut LEVEL: Level = Level::Debug;
+
+    pub fn level() -> Level {
+        unsafe { LEVEL }
+    }
+
+    /// Hides leveled logs below `level`. Plain `log!` and `println!` are not filtered.
+    pub fn set_level(level: Level) {
+        unsafe { LEVEL = level }
+    }
+
+    pub fn enabled(level: Level) -> bool {
+        level >= self::level()
+    }
+
+    /// Logs `text` with a level prefix if the level is enabled
+    pub fn log(level: Level, text: &str) {
+        log_args(level, format_args!("{}", text))
+    }
+
+    /// Logs formatted arguments with a level prefix if the level is enabled, reusing
+    /// the same buffer as `log!`. Used by the leveled log macros.
+    pub fn log_args(level: Level, args: std::fmt::Arguments) {
+        if enabled(level) {
+            super::with_formatted(format_args!("{} {}", level.prefix(), args), super::log)
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+pub mod config {
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+    use std::io;
+
+    /// Parses a TOML config into values by key. Keys inside a table, whether from a
+    /// `[section]`, a dotted key, or an inline table, are also flattened to
+    /// `section_key`, so a field can read either the whole table or one value.
+    pub fn parse(toml: &str) -> Result<BTreeMap<String, Value>, io::Error> {
+        let table = toml
+            .parse::<toml::Table>()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+        let mut values = BTreeMap::new();
+        for (key, value) in table {
+            flatten(&mut values, key, value)?;
+        }
+        Ok(values)
+    }
+
+    fn flatten(
+        values: &mut BTreeMap<String, Value>,
+        key: String,
+        value: toml::Value,
+    ) -> Result<(), io::Error> {
+        if let toml::Value::Table(table) = &value {
+            for (child, value) in table {
+                flatten(values, format!("{}_{}", key, child), value.clone())?;
+            }
+        }
+        let value = serde_json::to_value(value)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        values.insert(key, value);
+        Ok(())
+    }
+
+    /// Reads a typed field from parsed config values.
+    pub fn field<T: DeserializeOwned>(
+        values: &BTreeMap<String, Value>,
+        key: &str,
+    ) -> Result<T, io::Error> {
+        let value = values.get(key).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("missing config field `{}`", key),
+            )
+        })?;
+        serde_json::from_value(value.clone()).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("config field `{}`: {}", key, err),
+            )
+        })
+    }
+}
+
+pub mod random {
+    use crate::ffi;
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    // Seeded generator shared by the module-level functions. Lives in wasm
+    // memory, so it resets on hot-reload unless restored with `set_state`.
+    static mut SEEDED_RNG: Option<Rng> = None;
+
+    /// Deterministic pseudo-random number generator (SplitMix64).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+    pub struct Rng {
+        state: u64,
+    }
+
+    impl Rng {
+        pub fn new(seed: u64) -> Self {
+            Self { state: seed }
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        }
+
+        pub fn next_u32(&mut self) -> u32 {
+            (self.next_u64() >> 32) as u32
+        }
+
+        /// Returns a float in the range [0.0, 1.0)
+        pub fn next_f32(&mut self) -> f32 {
+            (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+        }
+
+        /// Returns a float in the range [0.0, 1.0)
+        pub fn next_f64(&mut self) -> f64 {
+            (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        /// Splits off an independent deterministic stream
+        pub fn fork(&mut self) -> Rng {
+            Rng::new(self.next_u64())
+        }
+
+        /// Returns an unbiased integer in the range [0, n). Returns 0 if `n` is 0.
+        pub fn below(&mut self, n: u64) -> u64 {
+            if n == 0 {
+                return 0;
+            }
+            // Reject the low values that would make `x % n` biased
+            let threshold = n.wrapping_neg() % n;
+            loop {
+                let x = self.next_u64();
+                if x >= threshold {
+                    return x % n;
+                }
+            }
+        }
+
+        /// Returns a value in the half-open range [min, max). Returns `min` if the range is empty.
+        pub fn range<T: RandomRange>(&mut self, min: T, max: T) -> T {
+            T::random_range(self, min, max)
+        }
+
+        pub fn choice<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+            if items.is_empty() {
+                return None;
+            }
+            items.get(self.below(items.len() as u64) as usize)
+        }
+
+        /// Picks an item with probability proportional to its weight.
+        /// Items with zero or negative weight are never picked.
+        pub fn weighted_choice<'a, T>(&mut self, items: &'a [(T, f32)]) -> Option<&'a T> {
+            let total: f32 = items.iter().map(|(_, w)| w.max(0.0)).sum();
+            if total <= 0.0 {
+                return None;
+            }
+            let mut n = self.next_f32() * total;
+            let mut last = None;
+            for (item, weight) in items.iter().filter(|(_, w)| *w > 0.0) {
+                if n < *weight {
+                    return Some(item);
+                }
+                n -= weight;
+                last = Some(item);
+            }
+            // Float rounding can leave a tiny remainder
+            last
+        }
+
+        /// Shuffles items in place (Fisher-Yates)
+        pub fn shuffle<T>(&mut self, items: &mut [T]) {
+            for i in (1..items.len()).rev() {
+                let j = self.below(i as u64 + 1) as usize;
+                items.swap(i, j);
+            }
+        }
+    }
+
+    /// A weighted table for repeated rolls, e.g. loot or spawns. Rolls binary
+    /// search the running weight totals, so large tables stay fast.
+    #[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+    pub struct Table<T> {
+        // Each item with the total weight up to and including it
+        entries: Vec<(T, u64)>,
+    }
+
+    impl<T> Default for Table<T> {
+        fn default() -> Self {
+            Self { entries: vec![] }
+        }
+    }
+
+    impl<T> Table<T> {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Adds an item. Items with a weight of 0 could never be rolled, so they're skipped.
+        pub fn add(&mut self, item: T, weight: u32) -> &mut Self {
+            if weight > 0 {
+                let total = self.total_weight() + weight as u64;
+                self.entries.push((item, total));
+            }
+            self
+        }
+
+        pub fn total_weight(&self) -> u64 {
+            self.entries.last().map_or(0, |(_, total)| *total)
+        }
+
+        pub fn len(&self) -> usize {
+            self.entries.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.entries.is_empty()
+        }
+
+        /// Picks an item with the shared generator. Returns `None` if the table is empty.
+        pub fn roll(&self) -> Option<&T> {
+            with_rng(|rng| self.roll_with(rng))
+        }
+
+        /// Picks an item with probability proportional to its weight
+        pub fn roll_with(&self, rng: &mut Rng) -> Option<&T> {
+            if self.is_empty() {
+                return None;
+            }
+            let n = rng.below(self.total_weight());
+            let i = self.entries.partition_point(|(_, total)| *total <= n);
+            self.entries.get(i).map(|(item, _)| item)
+        }
+    }
+
+    pub trait RandomRange: Sized {
+        fn random_range(rng: &mut Rng, min: Self, max: Self) -> Self;
+    }
+
+    macro_rules! impl_random_range_int {
+        ($($t:ty),*) => {$(
+            impl RandomRange for $t {
+                fn random_range(rng: &mut Rng, min: Self, max: Self) -> Self {
+                    if max <= min {
+                        return min;
+                    }
+                    let span = (max as i128 - min as i128) as u64;
+                    (min as i128 + rng.below(span) as i128) as $t
+                }
+            }
+        )*};
+    }
+    impl_random_range_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+    impl RandomRange for f32 {
+        fn random_range(rng: &mut Rng, min: Self, max: Self) -> Self {
+            let n = min + (max - min) * rng.next_f32();
+            // Rounding can land exactly on max
+            if n < max {
+                n
+            } else {
+                min
+            }
+        }
+    }
+
+    impl RandomRange for f64 {
+        fn random_range(rng: &mut Rng, min: Self, max: Self) -> Self {
+            let n = min + (max - min) * rng.next_f64();
+            // Rounding can land exactly on max
+            if n < max {
+                n
+            } else {
+                min
+            }
+        }
+    }
+
+    // Runs `f` with the seeded generator if there is one, otherwise with a
+    // generator freshly seeded from the host
+    fn with_rng<T>(f: impl FnOnce(&mut Rng) -> T) -> T {
+        unsafe {
+            match SEEDED_RNG {
+                Some(mut rng) => {
+                    let n = f(&mut rng);
+                    SEEDED_RNG = Some(rng);
+                    n
+                }
+                None => {
+                    let seed = ((ffi::sys::rand() as u64) << 32) | ffi::sys::rand() as u64;
+                    f(&mut Rng::new(seed))
+                }
+            }
+        }
+    }
+
+    /// Seeds the shared generator. All module-level random functions (and
+    /// `sys::rand`) draw from it until `unseed` is called.
+    pub fn seed(seed: u64) {
+        unsafe { SEEDED_RNG = Some(Rng::new(seed)) }
+    }
+
+    /// Returns to host-backed randomness
+    pub fn unseed() {
+        unsafe { SEEDED_RNG = None }
+    }
+
+    /// Returns the shared generator state so it can be stored in game state
+    pub fn state() -> Option<Rng> {
+        unsafe { SEEDED_RNG }
+    }
+
+    /// Restores a shared generator state, e.g. after a hot-reload
+    pub fn set_state(rng: Option<Rng>) {
+        unsafe { SEEDED_RNG = rng }
+    }
+
+    pub fn u32() -> u32 {
+        match state() {
+            Some(_) => with_rng(|rng| rng.next_u32()),
+            None => ffi::sys::rand(),
+        }
+    }
+
+    pub fn u64() -> u64 {
+        ((u32() as u64) << 32) | u32() as u64
+    }
+
+    /// Returns a float in the range [0.0, 1.0)
+    pub fn f32() -> f32 {
+        (u32() >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns an independent generator. Deterministic when seeded.
+    pub fn fork() -> Rng {
+        Rng::new(u64())
+    }
+
+    /// Returns a value in the half-open range [min, max)
+    pub fn range<T: RandomRange>(min: T, max: T) -> T {
+        with_rng(|rng| rng.range(min, max))
+    }
+
+    pub fn choice<T>(items: &[T]) -> Option<&T> {
+        with_rng(|rng| rng.choice(items))
+    }
+
+    pub fn weighted_choice<T>(items: &[(T, f32)]) -> Option<&T> {
+        with_rng(|rng| rng.weighted_choice(items))
+    }
+
+    pub fn shuffle<T>(items: &mut [T]) {
+        with_rng(|rng| rng.shuffle(items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_section_round_trip() {
+        let blob = local::join_section(b"game", b"entries");
+        assert_eq!(
+            local::split_section(&blob),
+            (&b"game"[..], Some(&b"entries"[..]))
+        );
+        // Saves without entries are left as they are
+        assert_eq!(local::join_section(b"game", b""), b"game");
+        assert_eq!(local::split_section(b"game"), (&b"game"[..], None));
+        // A length that overruns the blob isn't treated as a section
+        let mut bad = 99u32.to_le_bytes().to_vec();
+        bad.extend_from_slice(b"turbo/lc");
+        assert_eq!(local::split_section(&bad), (&bad[..], None));
+    }
+
+    #[test]
+    fn test_random_table() {
+        let mut rng = random::Rng::new(7);
+        let empty = random::Table::<u8>::new();
+        assert_eq!(empty.roll_with(&mut rng), None);
+        let mut table = random::Table::new();
+        table.add("common", 3).add("never", 0).add("rare", 1);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.total_weight(), 4);
+        let mut counts = [0; 2];
+        for _ in 0..4000 {
+            match table.roll_with(&mut rng) {
+                Some(&"common") => counts[0] += 1,
+                Some(&"rare") => counts[1] += 1,
+                other => panic!("unexpected roll {other:?}"),
+            }
+        }
+        assert!((2800..3200).contains(&counts[0]), "{counts:?}");
+        let bytes = table.try_to_vec().unwrap();
+        let restored = random::Table::<String>::try_from_slice(&bytes).unwrap();
+        assert_eq!(restored.total_weight(), 4);
+    }
+
+    #[test]
+    fn test_with_formatted() {
+        let mut out = vec![];
+        with_formatted(format_args!("plain"), |s| out.push(s.to_string()));
+        with_formatted(format_args!("n = {}", 3), |s| out.push(s.to_string()));
+        let long = "x".repeat(LOG_BUFFER_CAPACITY * 2);
+        with_formatted(format_args!("{long}!"), |s| out.push(s.to_string()));
+        // Formatting while the buffer is in use falls back to allocating
+        with_formatted(format_args!("outer {}", 1), |s| {
+            out.push(s.to_string());
+            with_formatted(format_args!("inner {}", 2), |s| out.push(s.to_string()));
+        });
+        assert_eq!(out[0], "plain");
+        assert_eq!(out[1], "n = 3");
+        assert_eq!(out[2], format!("{long}!"));
+        assert_eq!(out[3..], ["outer 1", "inner 2"]);
+    }
+
+    #[test]
+    fn test_console_level_filter() {
+        use console::Level;
+        assert!(console::enabled(Level::Debug));
+        console::set_level(Level::Warn);
+        assert!(!console::enabled(Level::Info));
+        assert!(console::enabled(Level::Warn));
+        assert!(console::enabled(Level::Error));
+        console::set_level(Level::Debug);
+    }
+
+    #[test]
+    fn test_seeded_random_is_deterministic() {
+        random::seed(42);
+        let a = (0..8).map(|_| random::u32()).collect::<Vec<_>>();
+        let mut fork_a = random::fork();
+        random::seed(42);
+        let b = (0..8).map(|_| rand()).collect::<Vec<_>>();
+        let mut fork_b = random::fork();
+        random::unseed();
+        assert_eq!(a, b);
+        assert_eq!(fork_a.next_u64(), fork_b.next_u64());
+    }
+
+    #[test]
+    fn test_rng_range_choice_shuffle() {
+        let mut rng = random::Rng::new(1);
+        for _ in 0..1000 {
+            let n = rng.range(-3, 3);
+            assert!((-3..3).contains(&n));
+            let f = rng.range(1.5, 2.5);
+            assert!((1.5..2.5).contains(&f));
+        }
+        assert_eq!(rng.range(5, 5), 5);
+        assert_eq!(rng.choice::<u8>(&[]), None);
+        assert_eq!(rng.weighted_choice(&[("a", 0.0), ("b", 1.0)]), Some(&"b"));
+        assert_eq!(rng.weighted_choice(&[("a", 0.0)]), None);
+
+        let mut a = [1, 2, 3, 4, 5, 6, 7, 8];
+        let mut b = a;
+        random::Rng::new(9).shuffle(&mut a);
+        random::Rng::new(9).shuffle(&mut b);
+        assert_eq!(a, b);
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_config_parse_fields() {
+        let values = config::parse(
+            "# comment\ntitle = \"Game\"\nresolution = [256, 144]\n\n[canvas]\nlayers = [\n  1,\n  2,\n]\n",
+        )
+        .unwrap();
+        assert_eq!(config::field::<String>(&values, "title").unwrap(), "Game");
+        assert_eq!(
+            config::field::<(u32, u32)>(&values, "resolution").unwrap(),
+            (256, 144)
+        );
+        assert_eq!(
+            config::field::<Vec<u8>>(&values, "canvas_layers").unwrap(),
+            vec![1, 2]
+        );
+        assert!(config::field::<u32>(&values, "title").is_err());
+        assert!(config::field::<u32>(&values, "missing").is_err());
+        assert!(config::parse("title").is_err());
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_config_parse_toml_syntax() {
+        let values = config::parse(
+            r#"
+title = 'Single # quoted' # trailing comment
+tags = ["a,]", "b"]
+window = { width = 320, height = 180 }
+audio.volume = 0.5
+
+[input.keys]
+jump = "space"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config::field::<String>(&values, "title").unwrap(),
+            "Single # quoted"
+        );
+        assert_eq!(
+            config::field::<Vec<String>>(&values, "tags").unwrap(),
+            ["a,]", "b"]
+        );
+        assert_eq!(config::field::<u32>(&values, "window_width").unwrap(), 320);
+        assert_eq!(
+            config::field::<std::collections::BTreeMap<String, u32>>(&values, "window").unwrap()
+                ["height"],
+            180
+        );
+        assert_eq!(config::field::<f32>(&values, "audio_volume").unwrap(), 0.5);
+        assert_eq!(
+            config::field::<String>(&values, "input_keys_jump").unwrap(),
+            "space"
+        );
+    }
+
+    #[test]
+    fn test_hash_is_fnv1a() {
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_timers() {
+        let mut every = time::Timer::every(3);
+        let fired: Vec<bool> = (10..17).map(|t| every.tick_at(t)).collect();
+        assert_eq!(fired, [false, false, false, true, false, false, true]);
+
+        let mut each_frame = time::Timer::every(0);
+        assert!((0..4).all(|t| each_frame.tick_at(t)));
+
+        let mut once = time::Timer::once(2);
+        let fired: Vec<bool> = (0..5).map(|t| once.tick_at(t)).collect();
+        assert_eq!(fired, [false, false, true, false, false]);
+        assert!(once.is_done());
+
+        // A reset tick counter restarts the countdown rather than underflowing
+        let mut every = time::Timer::every(2);
+        every.tick_at(100);
+        assert!(!every.tick_at(0));
+        assert!(every.tick_at(2));
+    }
+
+    #[test]
+    fn test_rng_f32_range() {
+        let mut rng = random::Rng::new(7);
+        for _ in 0..1000 {
+            let n = rng.next_f32();
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
 }
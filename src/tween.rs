@@ -231,6 +231,24 @@ where
         T::interpolate(eased_t, self.start, self.end)
     }
 
+    /// Reads the current value without updating the tween, so several properties
+    /// can share its timing. A tween that hasn't started reads as `start`, and a
+    /// finished one holds `end`.
+    pub fn sample(&self) -> T {
+        self.sample_at(sys::tick())
+    }
+
+    fn sample_at(&self, tick: usize) -> T {
+        let elapsed = self
+            .start_tick
+            .map_or(0, |start| tick.saturating_sub(start));
+        if self.duration == 0 || elapsed >= self.duration {
+            return self.end;
+        }
+        let t = elapsed as f64 / self.duration as f64;
+        T::interpolate(self.easing.apply(t), self.start, self.end)
+    }
+
     pub fn done(&mut self) -> bool {
         let _ = self.get(); // ensure get has been called before checking fields
         self.duration == 0 || self.elapsed >= self.duration
@@ -366,3 +384,27 @@ impl Interpolate<(u32, u32)> for (u32, u32) {
         (x as u32, y as u32)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_does_not_advance() {
+        let mut tween = Tween {
+            start: 0.0f32,
+            end: 10.0,
+            duration: 10,
+            elapsed: 0,
+            easing: Easing::default(),
+            start_tick: Some(100),
+        };
+        assert_eq!(tween.sample_at(100), 0.0);
+        assert_eq!(tween.sample_at(105), 5.0);
+        assert_eq!(tween.sample_at(105), 5.0);
+        assert_eq!(tween.sample_at(500), 10.0);
+        assert_eq!(tween.elapsed, 0);
+        tween.start_tick = None;
+        assert_eq!(tween.sample_at(500), 0.0);
+    }
+}